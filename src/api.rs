@@ -0,0 +1,165 @@
+//! JSON API, for tools which need the statements, proofs and outline as data
+use crate::statement::ExpressionRenderer;
+use crate::statement::HypInfo;
+use crate::statement::Renderer;
+use crate::statement::StepInfo;
+use metamath_knife::outline::OutlineNodeRef;
+use metamath_knife::statement::as_str;
+use metamath_knife::StatementType;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub(crate) struct ExpressionJson {
+    tokens: String,
+    rendered: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct DistinctVariablesJson {
+    tokens: Vec<String>,
+    rendered: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct CommentJson {
+    raw: String,
+    rendered: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct StatementJson {
+    label: String,
+    r#type: String,
+    hypotheses: Vec<HypInfo>,
+    assertion: ExpressionJson,
+    distinct_variables: Vec<DistinctVariablesJson>,
+    comment: Option<CommentJson>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ProofJson {
+    label: String,
+    is_proof: bool,
+    steps: Vec<StepInfo>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct OutlineJson {
+    name: String,
+    chapter_ref: usize,
+    level: String,
+    label: Option<String>,
+    comment: Option<CommentJson>,
+    children: Vec<OutlineJson>,
+}
+
+impl Renderer {
+    pub fn api_statement(&self, explorer: String, label: String) -> Option<StatementJson> {
+        let sref = self.db.statement(label.as_bytes())?;
+        let expression_renderer = self.get_expression_renderer(explorer)?;
+        let is_proof = sref.statement_type() == StatementType::Provable;
+        // Statements whose formula could not be parsed have no syntax breakdown
+        let no_steps = self.db.stmt_parse_result().get_formula(&sref).is_none();
+        let comment = sref.associated_comment().map(|cmt| {
+            let mut span = cmt.span();
+            span.start += 2;
+            span.end -= 3;
+            let buf = &cmt.segment().segment.buffer;
+            CommentJson {
+                raw: String::from_utf8_lossy(span.as_ref(buf)).trim().to_string(),
+                rendered: self.render_comment(buf, span, &expression_renderer),
+            }
+        });
+        let distinct_variables = self
+            .get_distinct_variables(&sref)?
+            .iter()
+            .map(|group| DistinctVariablesJson {
+                tokens: group
+                    .iter()
+                    .map(|&atom| as_str(self.db.name_result().atom_name(atom)).to_string())
+                    .collect(),
+                rendered: self.render_distinct_variables(group, &expression_renderer),
+            })
+            .collect();
+        Some(StatementJson {
+            r#type: Renderer::get_statement_type(&label, is_proof, no_steps),
+            hypotheses: self.get_hyps(&sref, &expression_renderer, is_proof)?,
+            assertion: ExpressionJson {
+                tokens: sref
+                    .math_iter()
+                    .map(|token| as_str(&token).to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                rendered: expression_renderer
+                    .render_statement(&sref, &self.db, is_proof)
+                    .unwrap_or_else(|e| format!("Could not format assertion : {}", e)),
+            },
+            distinct_variables,
+            comment,
+            label,
+        })
+    }
+
+    pub fn api_proof(
+        &self,
+        explorer: String,
+        label: String,
+        all_steps: bool,
+        substitutions: bool,
+    ) -> Option<ProofJson> {
+        let sref = self.db.statement(label.as_bytes())?;
+        let expression_renderer = self.get_expression_renderer(explorer)?;
+        let (is_proof, steps) =
+            self.get_steps(&sref, &expression_renderer, all_steps, substitutions);
+        Some(ProofJson {
+            label,
+            is_proof,
+            steps,
+        })
+    }
+
+    /// Outline node information, with the comment and the children only for the top node
+    fn get_outline_json(
+        &self,
+        node: &OutlineNodeRef,
+        expression_renderer: &ExpressionRenderer,
+        is_top: bool,
+    ) -> OutlineJson {
+        let stmt = node.get_statement();
+        let comment = stmt.as_heading_comment().filter(|_| is_top).map(|heading| {
+            let buf = &stmt.segment().segment.buffer;
+            CommentJson {
+                raw: String::from_utf8_lossy(heading.content.as_ref(buf))
+                    .trim()
+                    .to_string(),
+                rendered: self.render_comment(buf, heading.content, expression_renderer),
+            }
+        });
+        OutlineJson {
+            name: node.get_name().to_string(),
+            chapter_ref: node.get_ref(),
+            level: format!("{:?}", node.get_level()),
+            label: stmt
+                .is_assertion()
+                .then(|| as_str(stmt.label()).to_string()),
+            comment,
+            children: if is_top {
+                node.children_iter()
+                    .map(|child| self.get_outline_json(&child, expression_renderer, false))
+                    .collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    pub fn api_outline(&self, explorer: String, chapter_ref: usize) -> Option<OutlineJson> {
+        let expression_renderer = self.get_expression_renderer(explorer)?;
+        let node = if chapter_ref == 0 {
+            self.db.root_outline_node()
+        } else {
+            self.db.get_outline_node_by_ref(chapter_ref)
+        };
+        Some(self.get_outline_json(&node, &expression_renderer, true))
+    }
+}
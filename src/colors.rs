@@ -0,0 +1,150 @@
+//! Colours of the variables, according to the typecode of their floating hypothesis
+use crate::statement::ExpressionRenderer;
+use crate::statement::Renderer;
+use metamath_knife::nameck::Atom;
+use metamath_knife::statement::as_str;
+use metamath_knife::Database;
+use metamath_knife::StatementType;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Colours given to the typecodes which are not listed in the database's `htmlvarcolor`
+const DEFAULT_COLORS: [&str; 6] = ["blue", "red", "#C3C", "#080", "#F80", "#088"];
+
+/// An entry of the "Colors of variables" legend
+#[derive(Serialize)]
+pub(crate) struct VarColorInfo {
+    typecode: String,
+    color: String,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct VariableColors {
+    /// The typecode of each variable
+    typecodes: Arc<HashMap<Atom, Atom>>,
+    /// The colour of each variable typecode, and its index in the order of the database
+    colors: Arc<HashMap<Atom, (String, usize)>>,
+}
+
+/// The classes and styles of the `htmlvarcolor` legend of the database,
+/// like `<SPAN CLASS=wff STYLE="color:blue">wff</SPAN>`.
+pub(crate) fn var_color_classes(db: &Database) -> Vec<(String, String)> {
+    let typesetting = db.typesetting_result();
+    let var_color = typesetting
+        .html_var_color
+        .iter()
+        .map(|token| as_str(token))
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let span_regex =
+        Regex::new(r#"(?i)<span\s+class\s*=\s*["']?([\w-]+)["']?\s+style\s*=\s*["']([^"']*)["']"#)
+            .unwrap();
+    span_regex
+        .captures_iter(&var_color)
+        .map(|captures| (captures[1].to_string(), captures[2].to_string()))
+        .collect()
+}
+
+impl VariableColors {
+    pub(crate) fn new(db: &Database) -> Self {
+        let nset = db.name_result();
+        let mut typecodes = HashMap::new();
+        let mut order = vec![];
+        for sref in db
+            .statements()
+            .filter(|sref| sref.statement_type() == StatementType::Floating)
+        {
+            let mut tokens = sref.math_iter();
+            if let (Some(typecode), Some(variable)) = (tokens.next(), tokens.next()) {
+                let typecode = nset.get_atom(&typecode);
+                if !order.contains(&typecode) {
+                    order.push(typecode);
+                }
+                typecodes.insert(nset.get_atom(&variable), typecode);
+            }
+        }
+        let color_regex = Regex::new(r"(?i)(?:^|;)\s*color\s*:\s*([^;]+)").unwrap();
+        let class_colors: HashMap<String, String> = var_color_classes(db)
+            .into_iter()
+            .filter_map(|(class, style)| {
+                color_regex
+                    .captures(&style)
+                    .map(|captures| (class, captures[1].trim().to_string()))
+            })
+            .collect();
+        let mut defaults = DEFAULT_COLORS.iter().cycle();
+        let colors = order
+            .iter()
+            .enumerate()
+            .map(|(index, &typecode)| {
+                let color = class_colors
+                    .get(as_str(nset.atom_name(typecode)))
+                    .cloned()
+                    .unwrap_or_else(|| defaults.next().unwrap().to_string());
+                (typecode, (color, index))
+            })
+            .collect();
+        VariableColors {
+            typecodes: Arc::new(typecodes),
+            colors: Arc::new(colors),
+        }
+    }
+
+    /// The colour of the given variable, if it is a variable
+    pub(crate) fn color(&self, variable: Atom) -> Option<&str> {
+        self.typecodes
+            .get(&variable)
+            .and_then(|typecode| self.colors.get(typecode))
+            .map(|(color, _)| color.as_str())
+    }
+
+    /// The legend for the variables among the given tokens, in the order of the database
+    pub(crate) fn legend<'a>(
+        &self,
+        db: &Database,
+        tokens: impl Iterator<Item = &'a str>,
+    ) -> Vec<VarColorInfo> {
+        let nset = db.name_result();
+        let mut used = BTreeMap::new();
+        for token in tokens {
+            if let Some(typecode) = self.typecodes.get(&nset.get_atom(token.as_bytes())) {
+                if let Some((color, index)) = self.colors.get(typecode) {
+                    used.insert(
+                        *index,
+                        VarColorInfo {
+                            typecode: as_str(nset.atom_name(*typecode)).to_string(),
+                            color: color.clone(),
+                        },
+                    );
+                }
+            }
+        }
+        used.into_iter().map(|(_, info)| info).collect()
+    }
+}
+
+impl Renderer {
+    /// The legend for the variables of the given statements, for the pages listing statements
+    pub(crate) fn get_legend<'a>(
+        &self,
+        expression_renderer: &ExpressionRenderer,
+        labels: impl Iterator<Item = &'a str>,
+    ) -> Vec<VarColorInfo> {
+        let colors = match expression_renderer.variable_colors() {
+            Some(colors) => colors,
+            None => return vec![],
+        };
+        let tokens: Vec<String> = labels
+            .filter_map(|label| self.db.statement(label.as_bytes()))
+            .flat_map(|sref| {
+                sref.math_iter()
+                    .map(|token| as_str(&token).to_string())
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        colors.legend(&self.db, tokens.iter().map(String::as_str))
+    }
+}
@@ -0,0 +1,357 @@
+//! Configuration of the server, read from an optional TOML file.
+//! Command line flags override the values of the file.
+use crate::statement::DatabaseInfo;
+use clap::ArgMatches;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+const DEFAULT_ADDRESS: &str = "0.0.0.0";
+const DEFAULT_PORT: u16 = 3030;
+const DEFAULT_JOBS: usize = 8;
+const DEFAULT_STATIC_MAX_AGE: u32 = 31536000;
+const DEFAULT_STS_FORMAT: &str = "mathml";
+
+/// How an explorer typesets expressions
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Typesetting {
+    Ascii,
+    Unicode,
+    Latex,
+    Sts,
+}
+
+/// Where the pages of an STS or LaTeX explorer load MathJax from
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MathJax {
+    /// Use the `$h` header of the STS definition file, or MathJax's CDN for LaTeX
+    Header,
+    /// Load MathJax from the `static/mathjax` directory
+    Local,
+    /// Do not load MathJax, and rely on the browser's native MathML support.
+    /// LaTeX formulas are then only displayed as source.
+    Native,
+}
+
+impl Default for MathJax {
+    fn default() -> Self {
+        MathJax::Header
+    }
+}
+
+/// An explorer, displaying the pages of each database with a given typesetting
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ExplorerConfig {
+    /// The path segment under which the explorer is served, like `mpeuni`
+    pub(crate) path: String,
+    /// The name displayed in the typesetting navigation
+    pub(crate) name: String,
+    pub(crate) typesetting: Typesetting,
+    /// For the STS typesetting, the format of the STS definition file
+    #[serde(default = "default_sts_format")]
+    pub(crate) format: String,
+    /// For the STS and LaTeX typesettings, where to load MathJax from
+    #[serde(default)]
+    pub(crate) mathjax: MathJax,
+}
+
+/// A database to be loaded, and where to serve it
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DatabaseConfig {
+    pub(crate) file: String,
+    /// The name displayed in the database navigation, by default the file name
+    pub(crate) name: Option<String>,
+    /// The path under which the database is served, like `/set`.
+    /// By default, a single database is served at the root, and several ones under their file stem.
+    pub(crate) prefix: Option<String>,
+    /// The index file, which includes the bibliography
+    pub(crate) bib: Option<String>,
+    /// The STS definition file of each format, by default `{database}-{format}.mmts`
+    #[serde(default)]
+    pub(crate) sts: HashMap<String, String>,
+    /// The paths of the explorers serving this database, by default all the configured explorers.
+    /// The first one is the one the database links point to.
+    #[serde(default)]
+    pub(crate) explorers: Vec<String>,
+    #[serde(skip)]
+    pub(crate) info: DatabaseInfo,
+}
+
+/// The `cache-control` headers sent with the responses
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CacheConfig {
+    /// Maximum age of the static files, in seconds
+    #[serde(default = "default_static_max_age")]
+    pub(crate) static_max_age: u32,
+    /// Maximum age of the pages, in seconds. No header is sent by default.
+    pub(crate) page_max_age: Option<u32>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            static_max_age: DEFAULT_STATIC_MAX_AGE,
+            page_max_age: None,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// The addresses to listen to
+    #[serde(default)]
+    pub(crate) listen: Vec<SocketAddr>,
+    /// Number of threads to use for startup parsing and static export
+    #[serde(default = "default_jobs")]
+    pub(crate) jobs: usize,
+    /// Check that all constructs defined in the databases are covered by the STS files
+    #[serde(default)]
+    pub(crate) check_sts: bool,
+    /// Reload the databases when their files change
+    #[serde(default)]
+    pub(crate) watch: bool,
+    #[serde(default, rename = "database")]
+    pub(crate) databases: Vec<DatabaseConfig>,
+    #[serde(default, rename = "explorer")]
+    pub(crate) explorers: Vec<ExplorerConfig>,
+    #[serde(default)]
+    pub(crate) cache: CacheConfig,
+}
+
+fn default_sts_format() -> String {
+    DEFAULT_STS_FORMAT.to_string()
+}
+
+fn default_static_max_age() -> u32 {
+    DEFAULT_STATIC_MAX_AGE
+}
+
+fn default_jobs() -> usize {
+    DEFAULT_JOBS
+}
+
+/// The explorers served when none are configured, the structured one only with the `sts` feature
+fn default_explorers() -> Vec<ExplorerConfig> {
+    let mut explorers = vec![
+        ExplorerConfig {
+            path: "mpeascii".to_string(),
+            name: "Ascii".to_string(),
+            typesetting: Typesetting::Ascii,
+            format: default_sts_format(),
+            mathjax: MathJax::default(),
+        },
+        ExplorerConfig {
+            path: "mpeuni".to_string(),
+            name: "Unicode".to_string(),
+            typesetting: Typesetting::Unicode,
+            format: default_sts_format(),
+            mathjax: MathJax::default(),
+        },
+    ];
+    #[cfg(feature = "sts")]
+    explorers.push(ExplorerConfig {
+        path: "mpests".to_string(),
+        name: "Structured".to_string(),
+        typesetting: Typesetting::Sts,
+        format: default_sts_format(),
+        mathjax: MathJax::default(),
+    });
+    explorers.push(ExplorerConfig {
+        path: "mpelatex".to_string(),
+        name: "LaTeX".to_string(),
+        typesetting: Typesetting::Latex,
+        format: default_sts_format(),
+        mathjax: MathJax::default(),
+    });
+    explorers
+}
+
+impl DatabaseConfig {
+    fn new(file: &str) -> Self {
+        DatabaseConfig {
+            file: file.to_string(),
+            name: None,
+            prefix: None,
+            bib: None,
+            sts: HashMap::new(),
+            explorers: vec![],
+            info: DatabaseInfo::default(),
+        }
+    }
+
+    /// The STS definition file for the given format:
+    /// either the configured one, or the file named `{database}-{format}.mmts` next to the database
+    pub(crate) fn sts_file(&self, format: &str) -> String {
+        if let Some(file) = self.sts.get(format) {
+            return file.clone();
+        }
+        let path = Path::new(&self.file);
+        let stem = path
+            .file_stem()
+            .map_or(self.file.clone(), |stem| stem.to_string_lossy().to_string());
+        path.with_file_name(format!("{}-{}.mmts", stem, format))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// The configuration of the explorers serving this database, in the order of its `explorers` list
+    pub(crate) fn explorer_configs(&self, explorers: &[ExplorerConfig]) -> Vec<ExplorerConfig> {
+        self.explorers
+            .iter()
+            .filter_map(|path| explorers.iter().find(|explorer| &explorer.path == path))
+            .cloned()
+            .collect()
+    }
+}
+
+impl Config {
+    /// Reads the configuration file given with `--config`, if any, and applies the command line flags
+    pub(crate) fn new(args: &ArgMatches) -> Result<Config, String> {
+        let contents = match args.value_of("config") {
+            Some(file) => read_to_string(file)
+                .map_err(|e| format!("Could not read configuration file {}: {}", file, e))?,
+            None => "".to_string(),
+        };
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Could not parse configuration file: {}", e))?;
+        config.apply_args(args)?;
+        config.resolve()?;
+        Ok(config)
+    }
+
+    fn apply_args(&mut self, args: &ArgMatches) -> Result<(), String> {
+        if let Some(files) = args.values_of("database") {
+            self.databases = files.map(DatabaseConfig::new).collect();
+        }
+        if let Some(bib_files) = args.values_of("bib_file") {
+            for (database, bib_file) in self.databases.iter_mut().zip(bib_files) {
+                database.bib = Some(bib_file.to_string());
+            }
+        }
+        let port = args
+            .value_of("port")
+            .map(u16::from_str)
+            .transpose()
+            .map_err(|e| format!("Invalid port: {}", e))?;
+        if let Some(address) = args.value_of("address") {
+            let address = IpAddr::from_str(address)
+                .map_err(|e| format!("Invalid address {}: {}", address, e))?;
+            let port = port
+                .or_else(|| self.listen.first().map(SocketAddr::port))
+                .unwrap_or(DEFAULT_PORT);
+            self.listen = vec![SocketAddr::new(address, port)];
+        } else if let Some(port) = port {
+            // Only the port is given: keep the configured addresses
+            if self.listen.is_empty() {
+                let address = IpAddr::from_str(DEFAULT_ADDRESS).unwrap();
+                self.listen = vec![SocketAddr::new(address, port)];
+            } else {
+                for address in self.listen.iter_mut() {
+                    address.set_port(port);
+                }
+            }
+        }
+        if let Some(jobs) = args.value_of("jobs") {
+            self.jobs = usize::from_str(jobs).expect("validator should check this");
+        }
+        if args.is_present("check_sts") {
+            self.check_sts = true;
+        }
+        if args.is_present("watch") {
+            self.watch = true;
+        }
+        Ok(())
+    }
+
+    /// Fills in the default values, and the name and path of each database
+    fn resolve(&mut self) -> Result<(), String> {
+        if self.databases.is_empty() {
+            return Err(
+                "No database given, either on the command line or in the configuration file"
+                    .to_string(),
+            );
+        }
+        if self.jobs == 0 {
+            return Err("The number of jobs shall be at least 1".to_string());
+        }
+        if self.listen.is_empty() {
+            let address = IpAddr::from_str(DEFAULT_ADDRESS).unwrap();
+            self.listen = vec![SocketAddr::new(address, DEFAULT_PORT)];
+        }
+        if self.explorers.is_empty() {
+            self.explorers = default_explorers();
+        }
+        let several = self.databases.len() > 1;
+        for database in self.databases.iter_mut() {
+            let path = Path::new(&database.file);
+            let name = database.name.clone().unwrap_or_else(|| {
+                path.file_name().map_or(database.file.clone(), |name| {
+                    name.to_string_lossy().to_string()
+                })
+            });
+            let prefix = match &database.prefix {
+                Some(prefix) => prefix.trim_matches('/').to_string(),
+                None if several => path.file_stem().map_or(database.file.clone(), |stem| {
+                    stem.to_string_lossy().to_string()
+                }),
+                None => "".to_string(),
+            };
+            if prefix.contains('/') {
+                return Err(format!(
+                    "Database prefix {} shall be a single path segment",
+                    prefix
+                ));
+            }
+            let prefix = if prefix.is_empty() {
+                prefix
+            } else {
+                format!("/{}", prefix)
+            };
+            if database.explorers.is_empty() {
+                database.explorers = self
+                    .explorers
+                    .iter()
+                    .map(|explorer| explorer.path.clone())
+                    .collect();
+            }
+            if let Some(unknown) = database
+                .explorers
+                .iter()
+                .find(|&path| !self.explorers.iter().any(|explorer| &explorer.path == path))
+            {
+                return Err(format!(
+                    "Unknown explorer {} for database {}",
+                    unknown, name
+                ));
+            }
+            database.info = DatabaseInfo {
+                name,
+                home: format!("{}/{}/toc", prefix, database.explorers[0]),
+                prefix,
+            };
+        }
+        Ok(())
+    }
+
+    /// The databases listed in the navigation of the pages, only when several are served
+    pub(crate) fn database_infos(&self) -> Vec<DatabaseInfo> {
+        if self.databases.len() > 1 {
+            self.databases
+                .iter()
+                .map(|database| database.info.clone())
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
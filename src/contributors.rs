@@ -0,0 +1,263 @@
+//! Contributor index, built from the contribution notes found in the comments
+use crate::statement::encode_query_param;
+use crate::statement::Renderer;
+use crate::toc::NavInfo;
+use metamath_knife::statement::as_str;
+use metamath_knife::Database;
+use regex::Regex;
+use serde::Serialize;
+use serde::Serializer;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A date as found in contribution notes, like `5-Aug-1993`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ContribDate {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl ContribDate {
+    pub(crate) fn parse(date: &str) -> Option<Self> {
+        let mut parts = date.split('-');
+        let day = parts.next()?.parse().ok()?;
+        let month_name = parts.next()?;
+        let month = MONTHS.iter().position(|&m| m == month_name)? as u8 + 1;
+        let year = parts.next()?.parse().ok()?;
+        Some(Self { year, month, day })
+    }
+}
+
+impl fmt::Display for ContribDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}",
+            self.day,
+            MONTHS[self.month as usize - 1],
+            self.year
+        )
+    }
+}
+
+impl Serialize for ContribDate {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub(crate) enum ContributionKind {
+    Contributed,
+    Revised,
+    ProofShortened,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Contribution {
+    pub(crate) label: String,
+    pub(crate) kind: ContributionKind,
+    pub(crate) date: ContribDate,
+}
+
+/// The latest change made to a statement
+#[derive(Clone, Debug)]
+pub(crate) struct Change {
+    pub(crate) contributor: String,
+    pub(crate) contribution: Contribution,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ContributorIndex {
+    contributors: Arc<BTreeMap<String, Vec<Contribution>>>,
+    changes: Arc<Vec<Change>>,
+}
+
+#[derive(Serialize)]
+struct ContributorSummary {
+    name: String,
+    /// HTML id of the contributor's row
+    anchor: String,
+    link: String,
+    contributed: usize,
+    revised: usize,
+    shortened: usize,
+    total: usize,
+}
+
+#[derive(Serialize)]
+struct ContributorsPageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    contributors: Vec<ContributorSummary>,
+}
+
+#[derive(Serialize)]
+struct ContributorPageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    name: String,
+    link: String,
+    contributed: Vec<Contribution>,
+    revised: Vec<Contribution>,
+    shortened: Vec<Contribution>,
+}
+
+/// The link to the page of a contributor
+pub(crate) fn contributor_link(name: &str) -> String {
+    format!("contributors?name={}", encode_query_param(name))
+}
+
+/// A slug of a contributor name, usable as an HTML id, like `norman-megill` for "Norman Megill"
+fn contributor_anchor(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+impl ContributorIndex {
+    /// Builds the index by scanning the comments of all assertions with the contribution regex.
+    pub(crate) fn new(database: &Database, contrib_regex: &Regex) -> Self {
+        let mut contributors: BTreeMap<String, Vec<Contribution>> = BTreeMap::new();
+        let mut changes: Vec<Change> = vec![];
+        for sref in database.statements() {
+            if !sref.is_assertion() {
+                continue;
+            }
+            let cmt = match sref.associated_comment() {
+                Some(cmt) => cmt,
+                None => continue,
+            };
+            let comment = String::from_utf8_lossy(cmt.span().as_ref(&cmt.segment().segment.buffer));
+            let mut latest: Option<Change> = None;
+            for caps in contrib_regex.captures_iter(&comment) {
+                let kind = match &caps[1] {
+                    "Contributed" => ContributionKind::Contributed,
+                    "Revised" | "Modified" => ContributionKind::Revised,
+                    _ => ContributionKind::ProofShortened,
+                };
+                let name = caps[2].split_whitespace().collect::<Vec<&str>>().join(" ");
+                let date = match ContribDate::parse(&caps[3]) {
+                    Some(date) => date,
+                    None => continue,
+                };
+                let contribution = Contribution {
+                    label: as_str(sref.label()).to_string(),
+                    kind,
+                    date,
+                };
+                if latest
+                    .as_ref()
+                    .map_or(true, |change| change.contribution.date < date)
+                {
+                    latest = Some(Change {
+                        contributor: name.clone(),
+                        contribution: contribution.clone(),
+                    });
+                }
+                contributors
+                    .entry(name)
+                    .or_insert_with(Vec::new)
+                    .push(contribution);
+            }
+            changes.extend(latest);
+        }
+        // Newest first, and later statements in the database first for the same date
+        changes.reverse();
+        changes.sort_by(|a, b| b.contribution.date.cmp(&a.contribution.date));
+        for contributions in contributors.values_mut() {
+            contributions.sort_by(|a, b| b.date.cmp(&a.date));
+        }
+        Self {
+            contributors: Arc::new(contributors),
+            changes: Arc::new(changes),
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Vec<Contribution>> {
+        self.contributors.get(name)
+    }
+
+    /// The latest change of each statement, newest first
+    pub(crate) fn recent_changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &Vec<Contribution>)> {
+        self.contributors.iter()
+    }
+}
+
+impl Renderer {
+    pub fn render_contributors(&self, explorer: String) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let count = |contributions: &[Contribution], kind: ContributionKind| {
+            contributions.iter().filter(|c| c.kind == kind).count()
+        };
+        let mut contributors: Vec<ContributorSummary> = self
+            .contributors
+            .iter()
+            .map(|(name, contributions)| ContributorSummary {
+                name: name.clone(),
+                anchor: contributor_anchor(name),
+                link: contributor_link(name),
+                contributed: count(contributions, ContributionKind::Contributed),
+                revised: count(contributions, ContributionKind::Revised),
+                shortened: count(contributions, ContributionKind::ProofShortened),
+                total: contributions.len(),
+            })
+            .collect();
+        contributors.sort_by(|a, b| b.total.cmp(&a.total));
+        let info = ContributorsPageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            contributors,
+        };
+        Some(
+            self.templates
+                .render("contributors", &info)
+                .expect("Failed to render"),
+        )
+    }
+
+    pub fn render_contributor(&self, explorer: String, name: String) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let contributions = self.contributors.get(&name)?;
+        let of_kind = |kind: ContributionKind| -> Vec<Contribution> {
+            contributions
+                .iter()
+                .filter(|c| c.kind == kind)
+                .cloned()
+                .collect()
+        };
+        let info = ContributorPageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            contributed: of_kind(ContributionKind::Contributed),
+            revised: of_kind(ContributionKind::Revised),
+            shortened: of_kind(ContributionKind::ProofShortened),
+            link: contributor_link(&name),
+            name,
+        };
+        Some(
+            self.templates
+                .render("contributor", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
@@ -0,0 +1,137 @@
+//! Axioms and definitions a theorem depends on, like metamath.exe's
+//! "This theorem was proved from axioms" and "This theorem depends on definitions"
+use crate::statement::Renderer;
+use crate::uses::UsesIndex;
+use metamath_knife::formula::Label;
+use metamath_knife::statement::as_str;
+use metamath_knife::statement::StatementRef;
+use metamath_knife::Database;
+use metamath_knife::StatementType;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// Memoised transitive closure of the axioms and definitions used by each theorem
+#[derive(Clone, Default)]
+pub(crate) struct DependencyCache {
+    closures: Arc<Mutex<HashMap<Label, Arc<HashSet<Label>>>>>,
+}
+
+pub(crate) struct Dependencies {
+    pub(crate) axioms: Vec<String>,
+    pub(crate) definitions: Vec<String>,
+}
+
+impl DependencyCache {
+    /// The logical axioms and definitions directly referenced by the proof of `sref`,
+    /// and the theorems it references, whose dependencies shall be included.
+    fn direct_references(database: &Database, sref: &StatementRef) -> (Vec<Label>, Vec<Label>) {
+        let nset = database.name_result();
+        let provable = database.grammar_result().provable_typecode();
+        let mut axioms = vec![];
+        let mut theorems = vec![];
+        for token in UsesIndex::proof_labels(sref) {
+            let label = match nset.lookup_label(token) {
+                Some(lookup) => lookup.atom,
+                None => continue,
+            };
+            let used_sref = match database.statement_by_label(label) {
+                Some(used_sref) => used_sref,
+                None => continue,
+            };
+            match used_sref.statement_type() {
+                StatementType::Provable => theorems.push(label),
+                StatementType::Axiom
+                    if used_sref
+                        .math_iter()
+                        .next()
+                        .map_or(false, |typecode| nset.get_atom(&typecode) == provable) =>
+                {
+                    axioms.push(label)
+                }
+                _ => {}
+            }
+        }
+        (axioms, theorems)
+    }
+
+    /// The memoised closure of the given theorem, if it was already computed
+    fn cached(&self, label: &Label) -> Option<Arc<HashSet<Label>>> {
+        self.closures.lock().unwrap().get(label).cloned()
+    }
+
+    /// Computes the axioms and definitions used by the given theorem,
+    /// walking its proof and the proofs of all the theorems it references.
+    /// The cache is only locked for lookups and for storing the results,
+    /// so that other requests are not blocked during the walk.
+    fn closure(&self, database: &Database, root: Label) -> Arc<HashSet<Label>> {
+        if let Some(closure) = self.cached(&root) {
+            return closure;
+        }
+        let mut computed: HashMap<Label, Arc<HashSet<Label>>> = HashMap::new();
+        let mut stack: Vec<(Label, Option<(Vec<Label>, Vec<Label>)>)> = vec![(root, None)];
+        while let Some((label, references)) = stack.pop() {
+            if computed.contains_key(&label) {
+                continue;
+            }
+            match references {
+                None => {
+                    if let Some(closure) = self.cached(&label) {
+                        computed.insert(label, closure);
+                        continue;
+                    }
+                    let sref = match database.statement_by_label(label) {
+                        Some(sref) => sref,
+                        None => continue,
+                    };
+                    let references = DependencyCache::direct_references(database, &sref);
+                    let pending: Vec<Label> = references
+                        .1
+                        .iter()
+                        .filter(|theorem| !computed.contains_key(*theorem))
+                        .copied()
+                        .collect();
+                    stack.push((label, Some(references)));
+                    stack.extend(pending.into_iter().map(|theorem| (theorem, None)));
+                }
+                Some((axioms, theorems)) => {
+                    let mut closure: HashSet<Label> = axioms.into_iter().collect();
+                    for theorem in theorems {
+                        if let Some(dependencies) = computed.get(&theorem) {
+                            closure.extend(dependencies.iter());
+                        }
+                    }
+                    computed.insert(label, Arc::new(closure));
+                }
+            }
+        }
+        let closure = computed.get(&root).cloned().unwrap_or_default();
+        self.closures.lock().unwrap().extend(computed);
+        closure
+    }
+}
+
+impl Renderer {
+    pub(crate) fn get_dependencies(&self, sref: &StatementRef) -> Dependencies {
+        let nset = self.db.name_result();
+        let closure = match nset.lookup_label(sref.label()) {
+            Some(lookup) if sref.statement_type() == StatementType::Provable => {
+                self.dependencies.closure(&self.db, lookup.atom)
+            }
+            _ => Arc::default(),
+        };
+        let mut labels: Vec<&str> = closure
+            .iter()
+            .map(|&label| as_str(nset.atom_name(label)))
+            .collect();
+        labels.sort_unstable();
+        let (definitions, axioms): (Vec<&str>, Vec<&str>) = labels
+            .into_iter()
+            .partition(|label| label.starts_with("df-"));
+        Dependencies {
+            axioms: axioms.into_iter().map(str::to_string).collect(),
+            definitions: definitions.into_iter().map(str::to_string).collect(),
+        }
+    }
+}
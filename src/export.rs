@@ -0,0 +1,128 @@
+//! Static site export, rendering all pages into a directory tree
+use crate::statement::Renderer;
+use metamath_knife::outline::OutlineNodeRef;
+use metamath_knife::parser::HeadingLevel;
+use metamath_knife::statement::as_str;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+
+/// A page to be exported
+enum ExportPage {
+    Statement(String),
+    Toc(usize),
+}
+
+impl Renderer {
+    /// Collects the references of all the chapters below the given outline node
+    fn collect_chapters(node: &OutlineNodeRef, chapters: &mut Vec<usize>) {
+        for child in node.children_iter() {
+            if child.get_level() != HeadingLevel::Statement {
+                chapters.push(child.get_ref());
+                Renderer::collect_chapters(&child, chapters);
+            }
+        }
+    }
+
+    fn export_page(&self, out: &Path, explorer: &str, page: &ExportPage) -> Result<(), String> {
+        let (file_name, html) = match page {
+            ExportPage::Statement(label) => (
+                format!("{}.html", label),
+                self.render_statement(explorer.to_string(), label.clone(), false, false),
+            ),
+            ExportPage::Toc(0) => (
+                "toc.html".to_string(),
+                self.render_toc(explorer.to_string(), 0),
+            ),
+            ExportPage::Toc(chapter_ref) => (
+                format!("toc-{}.html", chapter_ref),
+                self.render_toc(explorer.to_string(), *chapter_ref),
+            ),
+        };
+        let html = html.ok_or_else(|| format!("Could not render {}/{}", explorer, file_name))?;
+        let path = out.join(explorer).join(&file_name);
+        fs::write(&path, html).map_err(|e| format!("Could not write {:?}: {}", path, e))
+    }
+
+    /// Renders every statement page and every table of contents page of all explorers
+    /// into the given directory, using `jobs` threads, and copies the static assets.
+    /// Pages are written under the same paths as served, with the `.html` extension,
+    /// and link to each other with that extension, so that any plain file server can host them.
+    pub fn export(&self, root: &Path, jobs: usize) -> Result<(), String> {
+        let renderer = &self.for_export();
+        let out = &root.join(self.database.prefix.trim_start_matches('/'));
+        let mut pages = vec![ExportPage::Toc(0)];
+        let mut chapters = vec![];
+        Renderer::collect_chapters(&self.db.root_outline_node(), &mut chapters);
+        pages.extend(chapters.into_iter().map(ExportPage::Toc));
+        pages.extend(
+            self.db
+                .statements()
+                .filter(|sref| sref.is_assertion())
+                .map(|sref| ExportPage::Statement(as_str(sref.label()).to_string())),
+        );
+
+        let explorers: Vec<String> = self
+            .get_typesettings()
+            .iter()
+            .map(|typesetting| typesetting.dir.clone())
+            .collect();
+        for explorer in &explorers {
+            let dir = out.join(explorer);
+            fs::create_dir_all(&dir).map_err(|e| format!("Could not create {:?}: {}", dir, e))?;
+        }
+
+        let work: Vec<(&str, &ExportPage)> = explorers
+            .iter()
+            .flat_map(|explorer| pages.iter().map(move |page| (explorer.as_str(), page)))
+            .collect();
+        let next = AtomicUsize::new(0);
+        let errors = Mutex::new(vec![]);
+        println!("Exporting {} pages...", work.len());
+        thread::scope(|scope| {
+            for _ in 0..jobs.max(1) {
+                scope.spawn(|| {
+                    while let Some(&(explorer, page)) =
+                        work.get(next.fetch_add(1, Ordering::Relaxed))
+                    {
+                        if let Err(error) = renderer.export_page(out, explorer, page) {
+                            errors.lock().unwrap().push(error);
+                        }
+                    }
+                });
+            }
+        });
+        let errors = errors.into_inner().unwrap();
+        for error in &errors {
+            eprintln!("{}", error);
+        }
+
+        copy_dir(Path::new("static"), &root.join("static"))?;
+
+        if errors.is_empty() {
+            println!("Done.");
+            Ok(())
+        } else {
+            Err(format!("{} pages could not be exported", errors.len()))
+        }
+    }
+}
+
+/// Copies a directory and its subdirectories, like the static assets and a local MathJax
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("Could not create {:?}: {}", to, e))?;
+    for entry in fs::read_dir(from).map_err(|e| format!("Could not read {:?}: {}", from, e))? {
+        let entry = entry.map_err(|e| format!("Could not read {:?}: {}", from, e))?;
+        let target = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .map_err(|e| format!("Could not copy to {:?}: {}", target, e))?;
+        }
+    }
+    Ok(())
+}
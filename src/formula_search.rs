@@ -0,0 +1,196 @@
+//! Formula search, like metamath.exe's `SEARCH` command for math strings
+use crate::colors::VarColorInfo;
+use crate::search::MAX_SEARCH_RESULTS;
+use crate::statement::encode_query_param;
+use crate::statement::escape_html;
+use crate::statement::ExpressionRenderer;
+use crate::statement::Renderer;
+use crate::toc::NavInfo;
+use metamath_knife::formula::Substitutions;
+use metamath_knife::statement::as_str;
+use metamath_knife::Formula;
+use metamath_knife::StatementType;
+use serde::Serialize;
+use std::ops::Deref;
+
+#[derive(Serialize)]
+struct FormulaResultInfo {
+    label: String,
+    expr: String,
+}
+
+#[derive(Serialize)]
+struct FormulaSearchPageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    link: String,
+    query: String,
+    parsed: bool,
+    error: Option<String>,
+    results: Vec<FormulaResultInfo>,
+    truncated: bool,
+    legend: Vec<VarColorInfo>,
+}
+
+/// An element of a math symbol pattern
+#[derive(Debug, PartialEq)]
+enum SymbolPattern {
+    /// `$*`, any sequence of symbols, possibly empty
+    Any,
+    /// `$?`, any single symbol
+    One,
+    Symbol(String),
+}
+
+impl SymbolPattern {
+    /// Parses a math symbol pattern. The pattern may match anywhere in the statement.
+    fn parse(query: &str) -> Vec<SymbolPattern> {
+        let mut pattern = vec![SymbolPattern::Any];
+        pattern.extend(query.split_whitespace().map(|token| match token {
+            "$*" => SymbolPattern::Any,
+            "$?" => SymbolPattern::One,
+            _ => SymbolPattern::Symbol(token.to_string()),
+        }));
+        pattern.push(SymbolPattern::Any);
+        pattern
+    }
+
+    /// Matches the tokens against the pattern, backtracking on the last `$*` wildcard.
+    fn matches<T: Deref<Target = [u8]>>(pattern: &[SymbolPattern], tokens: &[T]) -> bool {
+        let (mut p, mut t) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+        while t < tokens.len() {
+            match pattern.get(p) {
+                Some(SymbolPattern::Any) => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                }
+                Some(SymbolPattern::One) => {
+                    p += 1;
+                    t += 1;
+                }
+                Some(SymbolPattern::Symbol(symbol)) if symbol.as_bytes() == &*tokens[t] => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match backtrack {
+                    Some((star_p, star_t)) => {
+                        backtrack = Some((star_p, star_t + 1));
+                        p = star_p + 1;
+                        t = star_t + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|item| *item == SymbolPattern::Any)
+    }
+}
+
+impl Renderer {
+    /// Labels of the assertions whose math string matches the symbol pattern
+    fn search_symbols(&self, query: &str) -> Vec<String> {
+        let pattern = SymbolPattern::parse(query);
+        self.db
+            .statements()
+            .filter(|sref| sref.is_assertion())
+            .filter(|sref| SymbolPattern::matches(&pattern, &sref.math_iter().collect::<Vec<_>>()))
+            .map(|sref| as_str(sref.label()).to_string())
+            .collect()
+    }
+
+    /// Labels of the provable assertions whose formula unifies with the given pattern,
+    /// the variables of the pattern being metavariables.
+    fn search_formula(&self, pattern: &Formula) -> Vec<String> {
+        let provable = self.db.grammar_result().provable_typecode();
+        let nset = self.db.name_result();
+        self.db
+            .statements()
+            .filter(|sref| {
+                matches!(
+                    sref.statement_type(),
+                    StatementType::Axiom | StatementType::Provable
+                ) && sref
+                    .math_iter()
+                    .next()
+                    .map_or(false, |typecode| nset.get_atom(&typecode) == provable)
+            })
+            .filter(|sref| {
+                self.db
+                    .stmt_parse_result()
+                    .get_formula(sref)
+                    .map_or(false, |formula| {
+                        formula.unify(pattern, &mut Substitutions::new()).is_ok()
+                    })
+            })
+            .map(|sref| as_str(sref.label()).to_string())
+            .collect()
+    }
+
+    pub fn render_formula_search(
+        &self,
+        explorer: String,
+        query: String,
+        parsed: bool,
+    ) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let query = query.trim().to_string();
+        let mut error = None;
+        let labels = if query.is_empty() {
+            vec![]
+        } else if parsed {
+            let tokens: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+            match ExpressionRenderer::parse_math(&self.db, &tokens) {
+                Ok((pattern, _)) => self.search_formula(&pattern),
+                Err(e) => {
+                    error = Some(e);
+                    vec![]
+                }
+            }
+        } else {
+            self.search_symbols(&query)
+        };
+        let truncated = labels.len() > MAX_SEARCH_RESULTS;
+        let results: Vec<FormulaResultInfo> = labels
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .filter_map(|label| {
+                let sref = self.db.statement(label.as_bytes())?;
+                let expr = expression_renderer
+                    .render_statement(
+                        &sref,
+                        &self.db,
+                        sref.statement_type() == StatementType::Provable,
+                    )
+                    .unwrap_or_else(|e| format!("Could not format assertion : {}", e));
+                Some(FormulaResultInfo { label, expr })
+            })
+            .collect();
+        let legend = self.get_legend(
+            &expression_renderer,
+            results.iter().map(|result| result.label.as_str()),
+        );
+        let info = FormulaSearchPageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            link: format!(
+                "search-formula?q={}{}",
+                encode_query_param(&query),
+                if parsed { "&mode=parsed" } else { "" }
+            ),
+            query: escape_html(&query),
+            parsed,
+            error: error.as_deref().map(escape_html),
+            results,
+            truncated,
+            legend,
+        };
+        Some(
+            self.templates
+                .render("formula_search", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
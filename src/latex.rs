@@ -0,0 +1,110 @@
+//! LaTeX Typesetting, using the `latexdef` definitions of the `$t` typesetting comment
+use crate::config::MathJax;
+use crate::statement::escape_html;
+use metamath_knife::formula::Formula;
+use metamath_knife::statement::as_str;
+use metamath_knife::statement::StatementRef;
+use metamath_knife::Database;
+
+/// MathJax, used to typeset the formulas in the browser, by default
+const MATHJAX_CDN: &str = "https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-chtml.js";
+
+/// MathJax in the local `static` directory
+const LOCAL_MATHJAX: &str = "/static/mathjax/tex-mml-chtml.js";
+
+#[derive(Clone)]
+pub(crate) struct LatexRenderer {
+    pub(crate) database: Database,
+    pub(crate) mathjax: MathJax,
+}
+
+impl LatexRenderer {
+    /// This renderer, loading MathJax from the given place
+    pub(crate) fn with_mathjax(&self, mathjax: MathJax) -> Self {
+        LatexRenderer {
+            database: self.database.clone(),
+            mathjax,
+        }
+    }
+
+    /// The script displaying the LaTeX source, given where to load MathJax from.
+    /// Without MathJax, the formulas cannot be typeset, and only their source is shown.
+    pub(crate) fn get_header(&self) -> String {
+        match self.mathjax {
+            MathJax::Header => format!(
+                "<script src=\"/static/latex.js\" data-mathjax=\"{}\" defer></script>",
+                MATHJAX_CDN
+            ),
+            MathJax::Local => format!(
+                "<script src=\"/static/latex.js\" data-mathjax=\"{}\" defer></script>",
+                LOCAL_MATHJAX
+            ),
+            MathJax::Native => "<script src=\"/static/latex.js\" defer></script>".into(),
+        }
+    }
+
+    /// The LaTeX source for the given symbols
+    fn to_latex<'a>(&self, tokens: impl Iterator<Item = &'a [u8]>) -> Result<String, String> {
+        let typesetting = self.database.typesetting_result();
+        let defs = tokens
+            .map(|token| {
+                typesetting
+                    .get_latex_def(token)
+                    .map(|def| as_str(def).to_string())
+                    .ok_or(format!("Unknown symbol: {}", as_str(token)))
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        Ok(defs.join(" "))
+    }
+
+    /// Displays the LaTeX source of an assertion or a proof step, with an action to copy it
+    fn render_latex(latex: &str) -> String {
+        format!(
+            "<span class=\"latex\"><code>{}</code><button class=\"copy-latex\" title=\"Copy LaTeX\">Copy</button></span>",
+            escape_html(latex)
+        )
+    }
+
+    /// Renders a formula, preceded by the provable typecode if `use_provables` is set,
+    /// or by its own typecode otherwise
+    pub(crate) fn render_formula(
+        &self,
+        formula: &Formula,
+        use_provables: bool,
+    ) -> Result<String, String> {
+        let nset = self.database.name_result();
+        let typecode = if use_provables {
+            self.database.grammar_result().provable_typecode()
+        } else {
+            formula.get_typecode()
+        };
+        let symbols = formula
+            .as_ref(&self.database)
+            .into_iter()
+            .map(|symbol| nset.atom_name(symbol));
+        Ok(Self::render_latex(&self.to_latex(
+            std::iter::once(nset.atom_name(typecode)).chain(symbols),
+        )?))
+    }
+
+    pub(crate) fn render_symbols(&self, tokens: &[String]) -> Result<String, String> {
+        Ok(format!(
+            "<code class=\"latex\">{}</code>",
+            escape_html(&self.to_latex(tokens.iter().map(|token| token.as_bytes()))?)
+        ))
+    }
+
+    pub(crate) fn render_symbol(&self, token: &[u8]) -> Result<String, String> {
+        Ok(format!(
+            "<code class=\"latex\">{}</code>",
+            escape_html(&self.to_latex(std::iter::once(token))?)
+        ))
+    }
+
+    pub(crate) fn render_statement(&self, sref: &StatementRef) -> Result<String, String> {
+        let tokens: Vec<_> = sref.math_iter().collect();
+        Ok(Self::render_latex(
+            &self.to_latex(tokens.iter().map(|token| &token[..]))?,
+        ))
+    }
+}
@@ -1,23 +1,49 @@
+mod api;
+mod colors;
+mod config;
+mod contributors;
+mod dependencies;
+mod export;
+mod formula_search;
+mod latex;
+mod outline;
+mod proof_tree;
+mod recent;
+mod search;
 mod statement;
 #[cfg(feature = "sts")]
 mod sts;
 #[cfg(feature = "sts")]
 mod sts_parser;
+mod theorems;
 mod toc;
 mod uni;
+mod uses;
+mod watch;
 
+use crate::config::Config;
+use crate::config::DatabaseConfig;
+use crate::config::Typesetting;
+use crate::recent::DEFAULT_RECENT_COUNT;
+use crate::statement::DatabaseInfo;
 use crate::statement::Renderer;
+use crate::watch::SharedRenderer;
 use clap::crate_version;
 use clap::App as ClapApp;
+use clap::AppSettings;
 use clap::Arg;
 use clap::ArgMatches;
 use metamath_knife::database::DbOptions;
 use metamath_knife::Database;
 use std::collections::HashMap;
 use std::convert::Infallible;
-use std::net::IpAddr;
 use std::path::Path;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::RwLock;
+use warp::filters::BoxedFilter;
+use warp::http::Uri;
 use warp::reject::Rejection;
 use warp::Filter;
 
@@ -32,29 +58,38 @@ fn command_args() -> ArgMatches {
     ClapApp::new("metamath-web")
         .version(crate_version!())
         .about("A web server providing Metamath pages")
+        .setting(AppSettings::SubcommandPrecedenceOverArg)
         .arg(
             Arg::new("database")
-                .help("Database file to load")
-                .required(true)
+                .help("Database files to load, instead of the ones of the configuration file. If several are given, each one is served under its own path, named after the file")
+                .required_unless_present("config")
+                .takes_value(true)
+                .multiple_values(true),
+        )
+        .arg(
+            Arg::new("config")
+                .help("Configuration file, in TOML format")
+                .long("config")
+                .short('c')
                 .takes_value(true),
         )
         .arg(
             Arg::new("address")
-                .help("Address to serve")
-                .default_value("0.0.0.0")
+                .help("Address to serve [default: 0.0.0.0]")
                 .long("address")
-                .short('a'),
+                .short('a')
+                .takes_value(true),
         )
         .arg(
             Arg::new("port")
-                .help("Port to listen to")
-                .default_value("3030")
+                .help("Port to listen to [default: 3030]")
                 .long("port")
-                .short('p'),
+                .short('p')
+                .takes_value(true),
         )
         .arg(
             Arg::new("jobs")
-                .help("Number of threads to use for startup parsing")
+                .help("Number of threads to use for startup parsing and static export")
                 .long("jobs")
                 .short('j')
                 .takes_value(true)
@@ -62,10 +97,11 @@ fn command_args() -> ArgMatches {
         )
         .arg(
             Arg::new("bib_file")
-                .help("Index file, which includes the bibliography")
+                .help("Index file, which includes the bibliography. Repeat for each database, in the same order")
                 .long("bib")
                 .short('b')
-                .takes_value(true),
+                .takes_value(true)
+                .multiple_occurrences(true),
         )
         .arg(
             Arg::new("check_sts")
@@ -75,26 +111,45 @@ fn command_args() -> ArgMatches {
                 .long("check-sts")
                 .short('S'),
         )
+        .arg(
+            Arg::new("watch")
+                .help("Reload the database when the database file or its includes change")
+                .long("watch")
+                .short('w'),
+        )
+        .subcommand(
+            ClapApp::new("export")
+                .about("Export all pages as a static site, instead of serving them")
+                .arg(
+                    Arg::new("out")
+                        .help("Directory where to write the pages")
+                        .long("out")
+                        .short('o')
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
         .get_matches()
 }
 
-fn build_db(args: &ArgMatches) -> Result<Database, String> {
-    let job_count =
-        usize::from_str(args.value_of("jobs").unwrap_or("8")).expect("validator should check this");
+fn build_db(config: &Config, database: &DatabaseConfig) -> Result<Database, String> {
     let options = DbOptions {
         autosplit: false,
         incremental: true,
-        jobs: job_count,
+        jobs: config.jobs,
         ..Default::default()
     };
     let mut db = Database::new(options);
+    println!("Starting up {}...", database.info.name);
+    parse_db(&mut db, &database.file)?;
+    Ok(db)
+}
+
+/// Runs all the passes on the database.
+/// When called again on an existing database, unchanged segments are reused.
+fn parse_db(db: &mut Database, file: &str) -> Result<(), String> {
     let data: Vec<(String, Vec<u8>)> = Vec::new();
-    let start = args
-        .value_of("database")
-        .map(|x| x.to_owned())
-        .unwrap_or_else(|| data[0].0.clone());
-    println!("Starting up...");
-    db.parse(start, data);
+    db.parse(file.to_string(), data);
     db.scope_pass();
     let diag = db.diag_notations();
     if !diag.is_empty() {
@@ -105,22 +160,185 @@ fn build_db(args: &ArgMatches) -> Result<Database, String> {
     db.stmt_parse_pass();
     db.outline_pass();
     println!("Ready.");
-    Ok(db)
+    Ok(())
 }
 
 fn with_renderer(
-    renderer: Renderer,
+    renderer: SharedRenderer,
 ) -> impl Filter<Extract = (Renderer,), Error = Infallible> + Clone {
-    warp::any().map(move || renderer.clone())
+    warp::any().map(move || renderer.read().unwrap().clone())
 }
 
 pub async fn get_theorem(
     explorer: String,
     label: String,
+    query: HashMap<String, String>,
     renderer: Renderer,
 ) -> Result<impl warp::Reply, Rejection> {
     let label = label.replace(".html", "");
-    match renderer.render_statement(explorer, label) {
+    let tree = query.get("view").map_or(false, |view| view == "tree");
+    let all_steps = query.get("steps").map_or(false, |steps| steps == "all");
+    match renderer.render_statement(explorer, label, tree, all_steps) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_uses(
+    explorer: String,
+    label: String,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    match renderer.render_uses(explorer, label) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_contributors(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let html = match query.get("name") {
+        Some(name) => renderer.render_contributor(explorer, name.clone()),
+        None => renderer.render_contributors(explorer),
+    };
+    match html {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+/// Redirects a top-level page, like the contributor index or the search, to the one of the default explorer
+pub async fn redirect_to_explorer(
+    location: String,
+    query: String,
+) -> Result<impl warp::Reply, Rejection> {
+    let location = if query.is_empty() {
+        location
+    } else {
+        format!("{}?{}", location, query)
+    };
+    match Uri::from_str(&location) {
+        Ok(uri) => Ok(warp::redirect::temporary(uri)),
+        Err(_) => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_recent(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let count: usize = query
+        .get("count")
+        .map_or(Ok(DEFAULT_RECENT_COUNT), |c| c.parse())
+        .unwrap_or(DEFAULT_RECENT_COUNT);
+    match renderer.render_recent(explorer, count) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_outline(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let open = query
+        .get("open")
+        .map_or_else(Default::default, |open| outline::parse_open(open));
+    match renderer.render_outline(explorer, open) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_theorems(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let page: usize = query.get("page").map_or(Ok(1), |p| p.parse()).unwrap_or(1);
+    match renderer.render_theorems(explorer, page) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_search(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let query = query.get("q").cloned().unwrap_or_default();
+    match renderer.render_search(explorer, query) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_formula_search(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let parsed = query.get("mode").map_or(false, |mode| mode == "parsed");
+    let query = query.get("q").cloned().unwrap_or_default();
+    match renderer.render_formula_search(explorer, query, parsed) {
+        Some(html) => Ok(warp::reply::html(html)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_api_statement(
+    explorer: String,
+    label: String,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    match renderer.api_statement(explorer, label) {
+        Some(json) => Ok(warp::reply::json(&json)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_api_proof(
+    explorer: String,
+    label: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let all_steps = query.get("steps").map_or(false, |steps| steps == "all");
+    let substitutions = query.get("substitutions").map_or(false, |s| s == "true");
+    match renderer.api_proof(explorer, label, all_steps, substitutions) {
+        Some(json) => Ok(warp::reply::json(&json)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_api_outline(
+    explorer: String,
+    query: HashMap<String, String>,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let chapter_ref: usize = query.get("ref").map_or(Ok(0), |c| c.parse()).unwrap_or(0);
+    match renderer.api_outline(explorer, chapter_ref) {
+        Some(json) => Ok(warp::reply::json(&json)),
+        None => Err(warp::reject::not_found()),
+    }
+}
+
+pub async fn get_chapter(
+    explorer: String,
+    page: String,
+    renderer: Renderer,
+) -> Result<impl warp::Reply, Rejection> {
+    let chapter_ref: usize = page
+        .strip_prefix("toc-")
+        .and_then(|c| c.trim_end_matches(".html").parse().ok())
+        .ok_or_else(warp::reject::not_found)?;
+    match renderer.render_toc(explorer, chapter_ref) {
         Some(html) => Ok(warp::reply::html(html)),
         None => Err(warp::reject::not_found()),
     }
@@ -138,52 +356,276 @@ pub async fn get_toc(
     }
 }
 
+/// All the routes serving the pages of a database, whose files are in `dir`.
+/// The top-level `contributors` path, linked from comments, and `search` path redirect to the pages of `explorer_home`.
+fn database_routes(
+    renderer: SharedRenderer,
+    dir: String,
+    explorer_home: String,
+    max_age: Option<u32>,
+) -> BoxedFilter<(Box<dyn warp::Reply>,)> {
+    let toc_renderer = renderer.clone();
+    let chapter_renderer = renderer.clone();
+    let uses_renderer = renderer.clone();
+    let contributors_renderer = renderer.clone();
+    let recent_renderer = renderer.clone();
+    let outline_renderer = renderer.clone();
+    let theorems_renderer = renderer.clone();
+    let search_renderer = renderer.clone();
+    let formula_search_renderer = renderer.clone();
+    let api_statement_renderer = renderer.clone();
+    let api_proof_renderer = renderer.clone();
+    let api_outline_renderer = renderer.clone();
+    let api_statement = warp::path::param()
+        .and(warp::path("statement"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_renderer(api_statement_renderer))
+        .and_then(get_api_statement);
+    let api_proof = warp::path::param()
+        .and(warp::path("proof"))
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(api_proof_renderer))
+        .and_then(get_api_proof);
+    let api_outline = warp::path::param()
+        .and(warp::path("outline"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(api_outline_renderer))
+        .and_then(get_api_outline);
+    let api = warp::path("api")
+        .and(warp::path("v1"))
+        .and(api_statement.or(api_proof).or(api_outline));
+    let uses = warp::path::param()
+        .and(warp::path::param())
+        .and(warp::path("uses"))
+        .and(warp::path::end())
+        .and(with_renderer(uses_renderer))
+        .and_then(get_uses);
+    let chapter = warp::path::param()
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_renderer(chapter_renderer))
+        .and_then(get_chapter);
+    let theorems = warp::path::param()
+        .and(warp::path::param())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(renderer))
+        .and_then(get_theorem);
+    let toc = warp::path::param()
+        .and(warp::path("toc").or(warp::path("toc.html")).unify())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(toc_renderer))
+        .and_then(get_toc);
+    let contributors = warp::path::param()
+        .and(warp::path("contributors"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(contributors_renderer))
+        .and_then(get_contributors);
+    let contributors_redirect = warp::path("contributors")
+        .and(warp::path::end())
+        .map({
+            let location = format!("{}/contributors", explorer_home);
+            move || location.clone()
+        })
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and_then(redirect_to_explorer);
+    let search_redirect = warp::path("search")
+        .and(warp::path::end())
+        .map({
+            let location = format!("{}/search", explorer_home);
+            move || location.clone()
+        })
+        .and(warp::query::raw().or(warp::any().map(String::new)).unify())
+        .and_then(redirect_to_explorer);
+    let recent = warp::path::param()
+        .and(warp::path("recent"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(recent_renderer))
+        .and_then(get_recent);
+    let outline = warp::path::param()
+        .and(warp::path("outline"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(outline_renderer))
+        .and_then(get_outline);
+    let theorem_list = warp::path::param()
+        .and(warp::path("theorems"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(theorems_renderer))
+        .and_then(get_theorems);
+    let search = warp::path::param()
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(search_renderer))
+        .and_then(get_search);
+    let formula_search = warp::path::param()
+        .and(warp::path("search-formula"))
+        .and(warp::path::end())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_renderer(formula_search_renderer))
+        .and_then(get_formula_search);
+    let statics = warp::fs::dir(dir);
+    api.or(uses)
+        .or(chapter)
+        .or(theorems)
+        .or(toc)
+        .or(contributors)
+        .or(contributors_redirect)
+        .or(recent)
+        .or(outline)
+        .or(theorem_list)
+        .or(search)
+        .or(search_redirect)
+        .or(formula_search)
+        .or(statics)
+        .map(move |reply| match max_age {
+            Some(max_age) => Box::new(warp::reply::with_header(
+                reply,
+                "cache-control",
+                format!("public, max-age={}", max_age),
+            )) as Box<dyn warp::Reply>,
+            None => Box::new(reply) as Box<dyn warp::Reply>,
+        })
+        .boxed()
+}
+
 #[tokio::main]
 async fn main() {
     let args = command_args();
-    let path = Path::new(args.value_of("database").unwrap())
-        .parent()
-        .unwrap_or(Path::new("."))
-        .to_string_lossy()
-        .to_string();
-    let addr: IpAddr = args.value_of("address").unwrap().parse().unwrap();
-    let port: u16 = args.value_of("port").unwrap().parse().unwrap();
-    match build_renderer(args) {
-        Ok(renderer) => {
-            let toc_renderer = renderer.clone();
-            let theorems = warp::path::param()
-                .and(warp::path::param())
-                .and(with_renderer(renderer))
-                .and_then(get_theorem);
-            let toc = warp::path::param()
-                .and(warp::path("toc"))
-                .and(warp::query::<HashMap<String, String>>())
-                .and(with_renderer(toc_renderer))
-                .and_then(get_toc);
-            let res =
-                warp::path("static")
-                    .and(warp::fs::dir("static"))
-                    .map(|res: warp::fs::File| {
-                        warp::reply::with_header(res, "cache-control", "public, max-age=31536000")
-                    });
-            let statics = warp::fs::dir(path);
-            let routes = theorems.or(toc).or(res).or(statics);
-            warp::serve(routes).run((addr, port)).await;
-        }
+    let config = match Config::new(&args) {
+        Ok(config) => config,
         Err(message) => {
             println!("Error: {}", message);
+            return;
+        }
+    };
+    let export_dir = args
+        .subcommand_matches("export")
+        .and_then(|export_args| export_args.value_of("out"))
+        .map(PathBuf::from);
+    let databases = config.database_infos();
+    let mut routes: Option<BoxedFilter<(Box<dyn warp::Reply>,)>> = None;
+    let mut export_failed = false;
+    for database in &config.databases {
+        let renderer = match build_db(&config, database)
+            .and_then(|db| build_renderer(&config, database, &databases, db))
+        {
+            Ok(renderer) => renderer,
+            Err(message) => {
+                println!("Error: {}", message);
+                return;
+            }
+        };
+        if let Some(export_dir) = &export_dir {
+            if let Err(message) = renderer.export(export_dir, config.jobs) {
+                println!("Error: {}", message);
+                export_failed = true;
+            }
+            continue;
+        }
+        let path = Path::new(&database.file)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_string_lossy()
+            .to_string();
+        let renderer: SharedRenderer = Arc::new(RwLock::new(renderer));
+        if config.watch {
+            tokio::spawn(watch::watch(
+                config.clone(),
+                database.clone(),
+                databases.clone(),
+                renderer.clone(),
+            ));
+        }
+        let explorer_home = format!("{}/{}", database.info.prefix, database.explorers[0]);
+        let database_routes =
+            database_routes(renderer, path, explorer_home, config.cache.page_max_age);
+        let mounted = match database.info.prefix.strip_prefix('/') {
+            Some(segment) => warp::path(segment.to_string()).and(database_routes).boxed(),
+            None => database_routes,
+        };
+        routes = Some(match routes {
+            Some(routes) => routes.or(mounted).unify().boxed(),
+            None => mounted,
+        });
+    }
+    if export_failed {
+        std::process::exit(1);
+    }
+    if let Some(routes) = routes {
+        let cache_control = format!("public, max-age={}", config.cache.static_max_age);
+        let res =
+            warp::path("static")
+                .and(warp::fs::dir("static"))
+                .map(move |res: warp::fs::File| {
+                    warp::reply::with_header(res, "cache-control", cache_control.clone())
+                });
+        let routes = res.or(routes);
+        let servers: Vec<_> = config
+            .listen
+            .iter()
+            .map(|&address| tokio::spawn(warp::serve(routes.clone()).run(address)))
+            .collect();
+        for server in servers {
+            if let Err(e) = server.await {
+                println!("Error: {}", e);
+            }
         }
     }
 }
 
-fn build_renderer(args: ArgMatches) -> Result<Renderer, String> {
-    let db = build_db(&args)?;
+fn build_renderer(
+    config: &Config,
+    database: &DatabaseConfig,
+    databases: &[DatabaseInfo],
+    db: Database,
+) -> Result<Renderer, String> {
     #[cfg(feature = "sts")]
-    let sts = parse_sts(db.clone(), &args, "mathml")?;
-    let bib_file = args.value_of("bib_file");
+    let mut sts = HashMap::new();
+    let explorers = database.explorer_configs(&config.explorers);
+    #[cfg(feature = "sts")]
+    for explorer in &explorers {
+        if explorer.typesetting == Typesetting::Sts && !sts.contains_key(&explorer.format) {
+            let filename = database.sts_file(&explorer.format);
+            match parse_sts(db.clone(), &filename, config.check_sts)? {
+                Some(definition) => {
+                    sts.insert(explorer.format.clone(), definition);
+                }
+                None => println!(
+                    "Warning: STS definition file {} not found, the {} explorer of {} is disabled.",
+                    filename, explorer.path, database.info.name
+                ),
+            }
+        }
+    }
+    #[cfg(not(feature = "sts"))]
+    for explorer in &explorers {
+        if explorer.typesetting == Typesetting::Sts {
+            println!(
+                "Warning: the {} explorer of {} needs the sts feature to use {}, it is disabled.",
+                explorer.path,
+                database.info.name,
+                database.sts_file(&explorer.format)
+            );
+        }
+    }
+    #[cfg(not(feature = "sts"))]
+    if config.check_sts {
+        println!("Warning: the STS files can only be checked with the sts feature.");
+    }
     Ok(Renderer::new(
         db,
-        bib_file.map(str::to_string),
+        database.bib.clone(),
+        database.info.clone(),
+        databases.to_vec(),
+        explorers,
         #[cfg(feature = "sts")]
         sts,
     ))
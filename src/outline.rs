@@ -0,0 +1,180 @@
+//! Full-depth outline of the database, with collapsible headings.
+//! All the headings are rendered, and the headings expanded when the page is loaded
+//! are given by the `open` query parameter, which is kept up to date in the browser.
+//! The statements are only rendered within the expanded headings,
+//! those of the other headings are fetched from the JSON API when they are expanded.
+use crate::statement::Renderer;
+use crate::theorems::TheoremList;
+use crate::toc::LinkInfo;
+use crate::toc::NavInfo;
+use metamath_knife::outline::OutlineNodeRef;
+use metamath_knife::parser::HeadingLevel;
+use metamath_knife::Database;
+use metamath_knife::StatementType;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Number of assertions within a heading and its subheadings
+#[derive(Clone, Copy, Default)]
+struct OutlineCount {
+    theorems: usize,
+    axioms: usize,
+    definitions: usize,
+}
+
+/// The number of theorems, axioms and definitions within each heading
+#[derive(Clone, Default)]
+pub(crate) struct OutlineCounts {
+    counts: Arc<HashMap<usize, OutlineCount>>,
+}
+
+#[derive(Serialize)]
+struct OutlineNodeInfo {
+    name: String,
+    link: LinkInfo,
+    chapter_ref: usize,
+    stmt_level: bool,
+    open: bool,
+    /// Whether the statements of this heading are left to be fetched when it is expanded
+    pending: bool,
+    counts: String,
+    children: Vec<OutlineNodeInfo>,
+}
+
+#[derive(Serialize)]
+struct OutlinePageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    link: String,
+    counts: String,
+    children: Vec<OutlineNodeInfo>,
+}
+
+impl OutlineCount {
+    /// A description like "12 theorems, 1 axiom", omitting the kinds without any statement
+    fn describe(&self) -> String {
+        [
+            (self.theorems, "theorem"),
+            (self.axioms, "axiom"),
+            (self.definitions, "definition"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}{}", count, name, if *count > 1 { "s" } else { "" }))
+        .collect::<Vec<String>>()
+        .join(", ")
+    }
+}
+
+impl OutlineCounts {
+    pub(crate) fn new(database: &Database) -> Self {
+        let nset = database.name_result();
+        let provable = database.grammar_result().provable_typecode();
+        let mut counts: HashMap<usize, OutlineCount> = HashMap::new();
+        for sref in database.statements() {
+            if sref
+                .math_iter()
+                .next()
+                .map_or(true, |typecode| nset.get_atom(&typecode) != provable)
+            {
+                continue;
+            }
+            let increment: fn(&mut OutlineCount) = match sref.statement_type() {
+                StatementType::Provable => |count| count.theorems += 1,
+                StatementType::Axiom if sref.label().starts_with(b"df-") => {
+                    |count| count.definitions += 1
+                }
+                StatementType::Axiom => |count| count.axioms += 1,
+                _ => continue,
+            };
+            let mut node = TheoremList::get_heading(database.get_outline_node(sref));
+            while let Some(heading) = node {
+                increment(counts.entry(heading.get_ref()).or_default());
+                node = heading.parent();
+            }
+        }
+        Self {
+            counts: Arc::new(counts),
+        }
+    }
+
+    fn get(&self, chapter_ref: usize) -> OutlineCount {
+        self.counts.get(&chapter_ref).copied().unwrap_or_default()
+    }
+}
+
+/// Parses the `open` parameter, a comma separated list of chapter references
+pub(crate) fn parse_open(open: &str) -> BTreeSet<usize> {
+    open.split(',')
+        .filter_map(|chapter_ref| chapter_ref.trim().parse().ok())
+        .collect()
+}
+
+fn format_open(open: &BTreeSet<usize>) -> String {
+    open.iter()
+        .map(|chapter_ref| chapter_ref.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+impl Renderer {
+    fn get_outline_node_info(
+        &self,
+        node: OutlineNodeRef,
+        open: &BTreeSet<usize>,
+    ) -> OutlineNodeInfo {
+        let chapter_ref = node.get_ref();
+        let stmt_level = node.get_level() == HeadingLevel::Statement;
+        let is_open = open.contains(&chapter_ref);
+        let pending = !is_open
+            && node
+                .children_iter()
+                .any(|child| child.get_level() == HeadingLevel::Statement);
+        let children = node
+            .children_iter()
+            .filter(|child| is_open || child.get_level() != HeadingLevel::Statement)
+            .map(|child| self.get_outline_node_info(child, open))
+            .collect();
+        let counts = if stmt_level {
+            "".to_string()
+        } else {
+            self.outline_counts.get(chapter_ref).describe()
+        };
+        OutlineNodeInfo {
+            name: node.get_name().to_string(),
+            link: (&node).into(),
+            chapter_ref,
+            stmt_level,
+            open: is_open,
+            pending,
+            counts,
+            children,
+        }
+    }
+
+    pub fn render_outline(&self, explorer: String, open: BTreeSet<usize>) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let root = self.db.root_outline_node();
+        let counts = self.outline_counts.get(root.get_ref()).describe();
+        let children = root
+            .children_iter()
+            .map(|node| self.get_outline_node_info(node, &open))
+            .collect();
+        let info = OutlinePageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            link: format!("outline?open={}", format_open(&open)),
+            counts,
+            children,
+        };
+        Some(
+            self.templates
+                .render("outline", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
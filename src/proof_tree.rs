@@ -0,0 +1,94 @@
+//! Indented proof tree view, like metamath.exe's `/indent` proof display, as an alternative to the step table
+use crate::statement::StepInfo;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Subproofs deeper than this are displayed as separate trees after the proof's tree,
+/// so that the recursion of the tree building and of the template rendering stays bounded
+const MAX_TREE_DEPTH: usize = 32;
+
+#[derive(Serialize)]
+pub(crate) struct ProofNodeInfo {
+    step: StepInfo,
+    /// Whether this subproof is used by several steps
+    shared: bool,
+    /// Whether this subproof was already displayed, in which case its children are omitted
+    repeated: bool,
+    /// Whether this subproof is too deep, in which case its children are displayed in a separate tree
+    deferred: bool,
+    children: Vec<ProofNodeInfo>,
+}
+
+struct ProofTreeBuilder<'a> {
+    steps: &'a [StepInfo],
+    index: HashMap<&'a str, usize>,
+    references: HashMap<&'a str, usize>,
+    displayed: HashSet<usize>,
+    /// The subproofs left to be displayed as separate trees
+    deferred: BTreeSet<usize>,
+}
+
+impl<'a> ProofTreeBuilder<'a> {
+    /// Builds the subproof of the given step, at the given depth in its tree.
+    /// The roots of the trees, at depth 0, are displayed even if they were deferred.
+    fn build(&mut self, step_index: usize, depth: usize) -> ProofNodeInfo {
+        let step = &self.steps[step_index];
+        let repeated = !self.displayed.insert(step_index) && depth > 0;
+        let deferred = !repeated && depth >= MAX_TREE_DEPTH && !step.hyps.is_empty();
+        let children = if repeated {
+            vec![]
+        } else if deferred {
+            self.deferred.insert(step_index);
+            vec![]
+        } else {
+            step.hyps
+                .iter()
+                .filter_map(|hyp| self.index.get(hyp.as_str()).copied())
+                .collect::<Vec<usize>>()
+                .into_iter()
+                .map(|hyp_index| self.build(hyp_index, depth + 1))
+                .collect()
+        };
+        ProofNodeInfo {
+            step: step.clone(),
+            shared: self.references.get(step.id.as_str()).copied().unwrap_or(0) > 1,
+            repeated,
+            deferred,
+            children,
+        }
+    }
+}
+
+/// Builds the proof tree from the proof steps, the last step being the root,
+/// followed by the trees of the subproofs which were too deep to be displayed within it
+pub(crate) fn get_proof_trees(steps: &[StepInfo]) -> Vec<ProofNodeInfo> {
+    let mut references = HashMap::new();
+    for step in steps {
+        for hyp in &step.hyps {
+            *references.entry(hyp.as_str()).or_insert(0) += 1;
+        }
+    }
+    let mut builder = ProofTreeBuilder {
+        steps,
+        index: steps
+            .iter()
+            .enumerate()
+            .map(|(step_index, step)| (step.id.as_str(), step_index))
+            .collect(),
+        references,
+        displayed: HashSet::new(),
+        deferred: BTreeSet::new(),
+    };
+    let mut trees = vec![];
+    if !steps.is_empty() {
+        trees.push(builder.build(steps.len() - 1, 0));
+    }
+    // Deferred subproofs are displayed in the order of their steps, like in the step table
+    while let Some(&step_index) = builder.deferred.iter().next() {
+        builder.deferred.remove(&step_index);
+        trees.push(builder.build(step_index, 0));
+    }
+    trees
+}
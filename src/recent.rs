@@ -0,0 +1,81 @@
+//! Most recent changes, the equivalent of metamath.exe's `mmrecent` page
+use crate::colors::VarColorInfo;
+use crate::contributors::contributor_link;
+use crate::contributors::ContribDate;
+use crate::contributors::ContributionKind;
+use crate::statement::Renderer;
+use crate::toc::NavInfo;
+use metamath_knife::StatementType;
+use serde::Serialize;
+
+/// Number of changes listed if not specified
+pub(crate) const DEFAULT_RECENT_COUNT: usize = 100;
+
+#[derive(Serialize)]
+struct ChangeInfo {
+    label: String,
+    kind: ContributionKind,
+    date: ContribDate,
+    contributor: String,
+    contributor_link: String,
+    expr: String,
+}
+
+#[derive(Serialize)]
+struct RecentPageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    link: String,
+    count: usize,
+    changes: Vec<ChangeInfo>,
+    legend: Vec<VarColorInfo>,
+}
+
+impl Renderer {
+    pub fn render_recent(&self, explorer: String, count: usize) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let changes: Vec<ChangeInfo> = self
+            .contributors
+            .recent_changes()
+            .iter()
+            .take(count)
+            .filter_map(|change| {
+                let sref = self.db.statement(change.contribution.label.as_bytes())?;
+                let expr = expression_renderer
+                    .render_statement(
+                        &sref,
+                        &self.db,
+                        sref.statement_type() == StatementType::Provable,
+                    )
+                    .unwrap_or_else(|e| format!("Could not format assertion : {}", e));
+                Some(ChangeInfo {
+                    label: change.contribution.label.clone(),
+                    kind: change.contribution.kind,
+                    date: change.contribution.date,
+                    contributor_link: contributor_link(&change.contributor),
+                    contributor: change.contributor.clone(),
+                    expr,
+                })
+            })
+            .collect();
+        let legend = self.get_legend(
+            &expression_renderer,
+            changes.iter().map(|change| change.label.as_str()),
+        );
+        let info = RecentPageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            link: format!("recent?count={}", count),
+            count,
+            changes,
+            legend,
+        };
+        Some(
+            self.templates
+                .render("recent", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
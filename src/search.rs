@@ -0,0 +1,194 @@
+//! Label and full-text search
+use crate::colors::VarColorInfo;
+use crate::statement::encode_query_param;
+use crate::statement::escape_html;
+use crate::statement::Renderer;
+use crate::toc::NavInfo;
+use metamath_knife::statement::as_str;
+use metamath_knife::Database;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Maximum number of results listed for each kind of search
+pub(crate) const MAX_SEARCH_RESULTS: usize = 200;
+
+struct SearchEntry {
+    label: String,
+    text: String,
+}
+
+/// The labels and lowercase description comments of all assertions, in database order
+#[derive(Clone, Default)]
+pub(crate) struct SearchIndex {
+    entries: Arc<Vec<SearchEntry>>,
+}
+
+#[derive(Serialize)]
+struct SearchResultInfo {
+    label: String,
+    description: String,
+}
+
+#[derive(Serialize)]
+struct SearchPageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    link: String,
+    query: String,
+    error: Option<String>,
+    label_results: Vec<SearchResultInfo>,
+    label_results_truncated: bool,
+    text_results: Vec<SearchResultInfo>,
+    text_results_truncated: bool,
+    legend: Vec<VarColorInfo>,
+}
+
+/// How the query is matched against labels
+enum LabelPattern {
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl LabelPattern {
+    /// Queries between slashes are regular expressions,
+    /// queries containing `*` or `?` are wildcard patterns,
+    /// and other queries are label prefixes.
+    fn parse(query: &str) -> Result<Self, String> {
+        if query.len() > 1 && query.starts_with('/') && query.ends_with('/') {
+            Regex::new(&query[1..query.len() - 1])
+                .map(LabelPattern::Regex)
+                .map_err(|e| format!("Invalid regular expression: {}", e))
+        } else if query.contains(|c| c == '*' || c == '?') {
+            let pattern = regex::escape(query)
+                .replace(r"\*", ".*")
+                .replace(r"\?", ".");
+            Regex::new(&format!("^{}$", pattern))
+                .map(LabelPattern::Regex)
+                .map_err(|e| format!("Invalid wildcard pattern: {}", e))
+        } else {
+            Ok(LabelPattern::Prefix(query.to_string()))
+        }
+    }
+
+    fn matches(&self, label: &str) -> bool {
+        match self {
+            LabelPattern::Prefix(prefix) => label.starts_with(prefix.as_str()),
+            LabelPattern::Regex(regex) => regex.is_match(label),
+        }
+    }
+}
+
+impl SearchIndex {
+    pub(crate) fn new(database: &Database) -> Self {
+        let entries = database
+            .statements()
+            .filter(|sref| sref.is_assertion())
+            .map(|sref| SearchEntry {
+                label: as_str(sref.label()).to_string(),
+                text: sref
+                    .associated_comment()
+                    .map(|cmt| {
+                        String::from_utf8_lossy(cmt.span().as_ref(&cmt.segment().segment.buffer))
+                            .to_lowercase()
+                    })
+                    .unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            entries: Arc::new(entries),
+        }
+    }
+
+    /// Labels matching the given pattern
+    fn search_labels(&self, pattern: &LabelPattern) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|entry| pattern.matches(&entry.label))
+            .map(|entry| entry.label.as_str())
+            .collect()
+    }
+
+    /// Labels of the statements whose comments contain all the words of the query
+    fn search_text(&self, query: &str) -> Vec<&str> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if words.is_empty() {
+            return vec![];
+        }
+        self.entries
+            .iter()
+            .filter(|entry| words.iter().all(|word| entry.text.contains(word.as_str())))
+            .map(|entry| entry.label.as_str())
+            .collect()
+    }
+}
+
+impl Renderer {
+    fn get_search_results(
+        &self,
+        labels: Vec<&str>,
+        explorer: &str,
+    ) -> Option<(Vec<SearchResultInfo>, bool)> {
+        let expression_renderer = self.get_expression_renderer(explorer.to_string())?;
+        let truncated = labels.len() > MAX_SEARCH_RESULTS;
+        let results = labels
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .filter_map(|label| {
+                let sref = self.db.statement(label.as_bytes())?;
+                Some(SearchResultInfo {
+                    label: label.to_string(),
+                    description: self.render_description_summary(&sref, &expression_renderer),
+                })
+            })
+            .collect();
+        Some((results, truncated))
+    }
+
+    pub fn render_search(&self, explorer: String, query: String) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let query = query.trim().to_string();
+        let mut error = None;
+        let (label_results, label_results_truncated) = if query.is_empty() {
+            (vec![], false)
+        } else {
+            match LabelPattern::parse(&query) {
+                Ok(pattern) => {
+                    self.get_search_results(self.search.search_labels(&pattern), &explorer)?
+                }
+                Err(e) => {
+                    error = Some(e);
+                    (vec![], false)
+                }
+            }
+        };
+        let (text_results, text_results_truncated) =
+            self.get_search_results(self.search.search_text(&query), &explorer)?;
+        let legend = self.get_legend(
+            &expression_renderer,
+            label_results
+                .iter()
+                .chain(text_results.iter())
+                .map(|result| result.label.as_str()),
+        );
+        let info = SearchPageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            link: format!("search?q={}", encode_query_param(&query)),
+            query: escape_html(&query),
+            error: error.as_deref().map(escape_html),
+            label_results,
+            label_results_truncated,
+            text_results,
+            text_results_truncated,
+            legend,
+        };
+        Some(
+            self.templates
+                .render("search", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
@@ -1,11 +1,28 @@
+use crate::colors::VarColorInfo;
+use crate::colors::VariableColors;
+use crate::config::ExplorerConfig;
+use crate::config::MathJax;
+use crate::config::Typesetting;
+use crate::contributors::contributor_link;
+use crate::contributors::ContributorIndex;
+use crate::dependencies::DependencyCache;
+use crate::latex::LatexRenderer;
+use crate::outline::OutlineCounts;
+use crate::proof_tree::get_proof_trees;
+use crate::proof_tree::ProofNodeInfo;
+use crate::search::SearchIndex;
 #[cfg(feature = "sts")]
 use crate::sts::StsDefinition;
+use crate::theorems::TheoremList;
 use crate::toc::NavInfo;
 use crate::uni::UnicodeRenderer;
+use crate::uses::UsesIndex;
 use handlebars::Handlebars;
 use metamath_knife::comment_parser::CommentItem;
 use metamath_knife::comment_parser::CommentParser;
+use metamath_knife::formula::Substitutions;
 use metamath_knife::grammar::FormulaToken;
+use metamath_knife::nameck::Atom;
 use metamath_knife::proof::ProofTreeArray;
 use metamath_knife::statement::as_str;
 use metamath_knife::statement::StatementRef;
@@ -15,22 +32,46 @@ use metamath_knife::Formula;
 use metamath_knife::Span;
 use regex::{Captures, Regex};
 use serde::Serialize;
+#[cfg(feature = "sts")]
+use std::collections::HashMap;
 use std::sync::Arc;
 
 #[derive(Serialize)]
-struct HypInfo {
+pub(crate) struct HypInfo {
     label: String,
+    tokens: String,
     expr: String,
 }
 
-#[derive(Serialize)]
-struct StepInfo {
-    id: String,
-    hyps: Vec<String>,
+#[derive(Clone, Serialize)]
+pub(crate) struct StepInfo {
+    pub(crate) id: String,
+    pub(crate) hyps: Vec<String>,
     label: String,
+    tokens: String,
     expr: String,
     r#type: String,
     link: bool,
+    /// Whether this step constructs a syntax expression rather than proving an assertion
+    syntax: bool,
+    /// Only computed on request of the JSON API, which the pages query when a step's panel is expanded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    substitutions: Option<Vec<SubstitutionInfo>>,
+}
+
+/// The subformula substituted to a variable of the assertion referenced by a proof step
+#[derive(Clone, Serialize)]
+pub(crate) struct SubstitutionInfo {
+    variable: String,
+    expr: String,
+}
+
+/// What is common to all the steps of a proof or of a syntax breakdown
+struct StepsContext<'a> {
+    proof_tree: &'a ProofTreeArray,
+    expression_renderer: &'a ExpressionRenderer,
+    /// Whether to compute the substitutions made by each step
+    substitutions: bool,
 }
 
 #[derive(Serialize)]
@@ -43,14 +84,29 @@ struct PageInfo {
     expr: String,
     nav: NavInfo,
     hyps: Vec<HypInfo>,
+    dvs: Vec<String>,
     is_proof: bool,
     steps: Vec<StepInfo>,
+    all_steps: bool,
+    tree: Vec<ProofNodeInfo>,
+    uses: Vec<String>,
+    axioms: Vec<String>,
+    definitions: Vec<String>,
+    legend: Vec<VarColorInfo>,
+}
+
+/// A database served, and the path under which it is served
+#[derive(Clone, Default, Serialize)]
+pub(crate) struct DatabaseInfo {
+    pub(crate) name: String,
+    pub(crate) prefix: String,
+    pub(crate) home: String,
 }
 
 #[derive(Serialize)]
 pub(crate) struct TypesettingInfo {
-    dir: &'static str,
-    name: &'static str,
+    pub(crate) dir: String,
+    pub(crate) name: String,
 }
 
 #[derive(Clone)]
@@ -59,26 +115,56 @@ pub struct Renderer {
     pub(crate) db: Database,
     contrib_regex: Regex,
     discouraged_regex: Regex,
-    link_regex: Regex,
-    bibl_regex: Regex,
     bib_file: String,
-    math_regex: Regex,
-    underline_regex: Regex,
+    pub(crate) database: DatabaseInfo,
+    pub(crate) databases: Vec<DatabaseInfo>,
+    explorers: Vec<ExplorerConfig>,
     #[cfg(feature = "sts")]
-    sts: StsDefinition,
+    sts: HashMap<String, StsDefinition>,
     uni: UnicodeRenderer,
+    latex: LatexRenderer,
+    pub(crate) uses: UsesIndex,
+    pub(crate) dependencies: DependencyCache,
+    pub(crate) contributors: ContributorIndex,
+    pub(crate) theorems: TheoremList,
+    pub(crate) outline_counts: OutlineCounts,
+    pub(crate) search: SearchIndex,
+    /// Whether pages are rendered for the static export, linking to `.html` files and omitting the dynamic pages
+    pub(crate) exporting: bool,
 }
 
 #[derive(Clone)]
-enum ExpressionRenderer {
+pub(crate) enum ExpressionRenderer {
     Ascii,
     Unicode(UnicodeRenderer),
+    Latex(LatexRenderer),
     #[cfg(feature = "sts")]
     Sts(StsDefinition),
 }
 
+/// Escapes text, like user input or raw math tokens, before it is inserted in the page
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Encodes text, like a search query, as the value of a parameter in the query string of a link
+pub(crate) fn encode_query_param(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
 impl ExpressionRenderer {
-    fn render_statement(
+    pub(crate) fn render_statement(
         &self,
         sref: &StatementRef,
         database: &Database,
@@ -91,6 +177,7 @@ impl ExpressionRenderer {
                 use_provables,
             ),
             ExpressionRenderer::Unicode(uni) => uni.render_statement(sref),
+            ExpressionRenderer::Latex(latex) => latex.render_statement(sref),
             #[cfg(feature = "sts")]
             ExpressionRenderer::Sts(sts) => sts.render_formula(
                 &self.get_formula(sref, database, use_provables)?,
@@ -140,8 +227,12 @@ impl ExpressionRenderer {
         #[allow(unused_variables)] use_provables: bool,
     ) -> Result<String, String> {
         match self {
-            ExpressionRenderer::Ascii => Ok(format!("<pre>{}</pre>", formula.as_ref(database))),
+            ExpressionRenderer::Ascii => Ok(format!(
+                "<pre>{}</pre>",
+                escape_html(&formula.as_ref(database).to_string())
+            )),
             ExpressionRenderer::Unicode(uni) => uni.render_formula(formula),
+            ExpressionRenderer::Latex(latex) => latex.render_formula(formula, use_provables),
             #[cfg(feature = "sts")]
             ExpressionRenderer::Sts(sts) => sts.render_formula(formula, use_provables),
         }
@@ -155,8 +246,11 @@ impl ExpressionRenderer {
     ) -> Result<String, String> {
         match self {
             ExpressionRenderer::Ascii => Ok(format!(
-                "<pre> |- {}</pre>",
-                &String::from_utf8_lossy(&proof_tree.exprs().unwrap()[tree_index])
+                "<pre>{} {}</pre>",
+                if use_provables { " |-" } else { "" },
+                escape_html(&String::from_utf8_lossy(
+                    &proof_tree.exprs().unwrap()[tree_index]
+                ))
             )),
             ExpressionRenderer::Unicode(uni) => {
                 uni.render_formula(&ExpressionRenderer::as_formula(
@@ -166,6 +260,15 @@ impl ExpressionRenderer {
                     use_provables,
                 )?)
             }
+            ExpressionRenderer::Latex(latex) => latex.render_formula(
+                &ExpressionRenderer::as_formula(
+                    &latex.database,
+                    proof_tree,
+                    tree_index,
+                    use_provables,
+                )?,
+                use_provables,
+            ),
             #[cfg(feature = "sts")]
             ExpressionRenderer::Sts(sts) => sts.render_formula(
                 &ExpressionRenderer::as_formula(
@@ -179,10 +282,102 @@ impl ExpressionRenderer {
         }
     }
 
-    fn get_header(&self) -> String {
+    fn render_variable(&self, atom: Atom, database: &Database) -> Result<String, String> {
+        let token = database.name_result().atom_name(atom);
+        match self {
+            ExpressionRenderer::Ascii => Ok(format!("<code>{}</code>", as_str(token))),
+            ExpressionRenderer::Unicode(uni) => uni.render_symbol(token),
+            ExpressionRenderer::Latex(latex) => latex.render_symbol(token),
+            #[cfg(feature = "sts")]
+            ExpressionRenderer::Sts(sts) => sts.render_variable(atom),
+        }
+    }
+
+    /// Renders a subformula, without its typecode
+    fn render_subformula(&self, formula: &Formula, database: &Database) -> String {
+        let nset = database.name_result();
+        let tokens = formula
+            .as_ref(database)
+            .into_iter()
+            .map(|atom| as_str(nset.atom_name(atom)).to_string())
+            .collect::<Vec<String>>();
+        match self {
+            #[cfg(feature = "sts")]
+            ExpressionRenderer::Sts(sts) => sts
+                .render_inline(formula, false)
+                .unwrap_or_else(|_| format!("<code>{}</code>", escape_html(&tokens.join(" ")))),
+            _ => self.render_math(&tokens, database),
+        }
+    }
+
+    /// Renders the math tokens found between backquotes in comments
+    pub(crate) fn render_math(
+        &self,
+        tokens: &[String],
+        #[allow(unused_variables)] database: &Database,
+    ) -> String {
+        match self {
+            ExpressionRenderer::Ascii => {
+                Ok(format!("<code>{}</code>", escape_html(&tokens.join(" "))))
+            }
+            ExpressionRenderer::Unicode(uni) => uni.render_symbols(tokens),
+            ExpressionRenderer::Latex(latex) => latex.render_symbols(tokens),
+            #[cfg(feature = "sts")]
+            ExpressionRenderer::Sts(sts) => ExpressionRenderer::parse_math(database, tokens)
+                .and_then(|(formula, use_provables)| sts.render_inline(&formula, use_provables)),
+        }
+        .unwrap_or_else(|_| format!("<code>{}</code>", escape_html(&tokens.join(" "))))
+    }
+
+    /// Parses math tokens from a comment or a search pattern, which may or may not start with a typecode.
+    /// Also returns whether the typecode is the provable typecode.
+    pub(crate) fn parse_math(
+        database: &Database,
+        tokens: &[String],
+    ) -> Result<(Formula, bool), String> {
+        let nset = database.name_result();
+        let grammar = database.grammar_result();
+        let formula_string = tokens.join(" ");
+        let first_atom = tokens
+            .first()
+            .map(|token| nset.get_atom(token.as_bytes()))
+            .ok_or("Empty formula")?;
+        if first_atom == grammar.provable_typecode() || grammar.typecodes().contains(&first_atom) {
+            return grammar
+                .parse_string(&formula_string, nset)
+                .map(|formula| (formula, first_atom == grammar.provable_typecode()))
+                .map_err(|e| format!("Could not parse formula: {:?}", e));
+        }
+        grammar
+            .typecodes()
+            .iter()
+            .find_map(|tc| {
+                grammar
+                    .parse_string(
+                        format!("{} {}", as_str(nset.atom_name(*tc)), formula_string).as_str(),
+                        nset,
+                    )
+                    .ok()
+            })
+            .map(|formula| (formula, false))
+            .ok_or_else(|| format!("{} - Could not parse formula", formula_string))
+    }
+
+    /// The colours of the variables, for the typesettings which colour them
+    pub(crate) fn variable_colors(&self) -> Option<&VariableColors> {
+        match self {
+            ExpressionRenderer::Unicode(uni) => Some(&uni.colors),
+            #[cfg(feature = "sts")]
+            ExpressionRenderer::Sts(sts) => sts.colors.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get_header(&self) -> String {
         match self {
             ExpressionRenderer::Ascii => "".to_string(),
             ExpressionRenderer::Unicode(uni) => uni.get_header(),
+            ExpressionRenderer::Latex(latex) => latex.get_header(),
             #[cfg(feature = "sts")]
             ExpressionRenderer::Sts(sts) => sts.header.clone(),
         }
@@ -221,7 +416,10 @@ impl Renderer {
     pub(crate) fn new(
         db: Database,
         bib_file: Option<String>,
-        #[cfg(feature = "sts")] sts: StsDefinition,
+        database: DatabaseInfo,
+        databases: Vec<DatabaseInfo>,
+        explorers: Vec<ExplorerConfig>,
+        #[cfg(feature = "sts")] sts: HashMap<String, StsDefinition>,
     ) -> Renderer {
         let mut templates = Handlebars::new();
         templates.register_escape_fn(handlebars::no_escape);
@@ -231,56 +429,122 @@ impl Renderer {
         templates
             .register_template_string("toc", include_str!("toc.hbs"))
             .expect("Unable to parse table of contents template.");
+        templates
+            .register_template_string("uses", include_str!("uses.hbs"))
+            .expect("Unable to parse uses template.");
+        templates
+            .register_template_string("contributors", include_str!("contributors.hbs"))
+            .expect("Unable to parse contributors template.");
+        templates
+            .register_template_string("contributor", include_str!("contributor.hbs"))
+            .expect("Unable to parse contributor template.");
+        templates
+            .register_template_string("recent", include_str!("recent.hbs"))
+            .expect("Unable to parse recent changes template.");
+        templates
+            .register_template_string("theorems", include_str!("theorems.hbs"))
+            .expect("Unable to parse theorem list template.");
+        templates
+            .register_template_string("search", include_str!("search.hbs"))
+            .expect("Unable to parse search template.");
+        templates
+            .register_template_string("formula_search", include_str!("formula_search.hbs"))
+            .expect("Unable to parse formula search template.");
+        templates
+            .register_template_string("outline", include_str!("outline.hbs"))
+            .expect("Unable to parse outline template.");
+        templates
+            .register_partial("outline_node", include_str!("outline_node.hbs"))
+            .expect("Unable to parse outline node template.");
+        templates
+            .register_partial("proof_node", include_str!("proof_node.hbs"))
+            .expect("Unable to parse proof node template.");
+        templates
+            .register_partial("legend", include_str!("legend.hbs"))
+            .expect("Unable to parse legend template.");
+        templates
+            .register_partial("head", include_str!("head.hbs"))
+            .expect("Unable to parse head template.");
+        templates
+            .register_partial("nav", include_str!("nav.hbs"))
+            .expect("Unable to parse navigation template.");
         let contrib_regex = Regex::new(r"\((Contributed|Revised|Modified|Proof[ \n]+shortened)[ \n]+by[ \n]+(?s)(.+?),[ \n]+(\d{1,2}-\w\w\w-\d{4})\.\)").unwrap();
         let discouraged_regex =
             Regex::new(r"\(New usage is discouraged\.\)|\(Proof modification is discouraged\.\)")
                 .unwrap();
-        let math_regex = Regex::new(r"` (:?[^`]+) `").unwrap();
-        let link_regex = Regex::new(r"\~ ([^ \n]+)[ \n]+").unwrap();
-        let bibl_regex = Regex::new(r"\[([^ \n]+)\]").unwrap();
-        let underline_regex = Regex::new(r"[ \n]_([^_]+)_").unwrap();
+        let contributors = ContributorIndex::new(&db, &contrib_regex);
         Renderer {
             templates: Arc::new(templates),
             db: db.clone(),
             contrib_regex,
             discouraged_regex,
-            link_regex,
-            bibl_regex,
             bib_file: bib_file.unwrap_or("".to_string()),
-            math_regex,
-            underline_regex,
-            uni: UnicodeRenderer { database: db },
+            database,
+            databases,
+            explorers,
+            uses: UsesIndex::new(&db),
+            dependencies: DependencyCache::default(),
+            contributors,
+            theorems: TheoremList::new(&db),
+            outline_counts: OutlineCounts::new(&db),
+            search: SearchIndex::new(&db),
+            uni: UnicodeRenderer::new(db.clone()),
+            latex: LatexRenderer {
+                database: db,
+                mathjax: MathJax::default(),
+            },
             #[cfg(feature = "sts")]
             sts,
+            exporting: false,
         }
     }
 
-    fn get_expression_renderer(&self, explorer: String) -> Option<ExpressionRenderer> {
-        match explorer.as_str() {
-            "mpeascii" => Some(ExpressionRenderer::Ascii),
-            "mpeuni" => Some(ExpressionRenderer::Unicode(self.uni.clone())),
-            #[cfg(feature = "sts")]
-            "mpests" => Some(ExpressionRenderer::Sts(self.sts.clone())),
-            _ => None,
+    /// A copy of this renderer, rendering pages for the static export
+    pub(crate) fn for_export(&self) -> Renderer {
+        Renderer {
+            exporting: true,
+            ..self.clone()
         }
     }
 
-    pub(crate) fn get_typesettings() -> Vec<TypesettingInfo> {
-        vec![
-            TypesettingInfo {
-                dir: "mpeascii",
-                name: "Ascii",
-            },
-            TypesettingInfo {
-                dir: "mpeuni",
-                name: "Unicode",
-            },
+    pub(crate) fn get_expression_renderer(&self, explorer: String) -> Option<ExpressionRenderer> {
+        let explorer = self
+            .explorers
+            .iter()
+            .find(|config| config.path == explorer)?;
+        match explorer.typesetting {
+            Typesetting::Ascii => Some(ExpressionRenderer::Ascii),
+            Typesetting::Unicode => Some(ExpressionRenderer::Unicode(self.uni.clone())),
+            Typesetting::Latex => Some(ExpressionRenderer::Latex(
+                self.latex.with_mathjax(explorer.mathjax),
+            )),
             #[cfg(feature = "sts")]
-            TypesettingInfo {
-                dir: "mpests",
-                name: "Structured",
-            },
-        ]
+            Typesetting::Sts => self.sts.get(&explorer.format).map(|sts| {
+                let sts = sts.with_mathjax(explorer.mathjax);
+                if explorer.format == "mathml" {
+                    ExpressionRenderer::Sts(sts.with_colors(self.uni.colors.clone()))
+                } else {
+                    ExpressionRenderer::Sts(sts)
+                }
+            }),
+            #[cfg(not(feature = "sts"))]
+            Typesetting::Sts => None,
+        }
+    }
+
+    /// The explorers available for this database
+    pub(crate) fn get_typesettings(&self) -> Vec<TypesettingInfo> {
+        self.explorers
+            .iter()
+            .filter(|explorer| {
+                self.get_expression_renderer(explorer.path.clone())
+                    .is_some()
+            })
+            .map(|explorer| TypesettingInfo {
+                dir: explorer.path.clone(),
+                name: explorer.name.clone(),
+            })
+            .collect()
     }
 
     fn stmt_type(stmt: StatementRef) -> String {
@@ -293,72 +557,108 @@ impl Renderer {
         }
     }
 
-    pub(crate) fn render_comment(&self, comment: &str) -> String {
-        let comment = comment.replace("\n\n", "</p>\n<p>");
-        let comment = self.contrib_regex.replace_all(&comment, |caps: &Captures| {
-            format!(
-                "<span class=\"contrib\">({} by <a href=\"/contributors#{}\">{}</a>, {})</span>",
-                caps.get(1)
-                    .expect("Contribution Regex did not return a contribution type")
-                    .as_str(),
-                caps.get(2)
-                    .expect("Contribution Regex did not return a contributor")
-                    .as_str(),
-                caps.get(2)
-                    .expect("Contribution Regex did not return a contributor")
-                    .as_str(),
-                caps.get(3)
-                    .expect("Contribution Regex did not return a contribution date")
-                    .as_str(),
-            )
-        });
-        let comment = self
-            .discouraged_regex
-            .replace_all(&comment, |caps: &Captures| {
+    /// Groups the mandatory distinct variable pairs of a frame,
+    /// the way metamath.exe displays them: each group is a set of variables
+    /// which are all pairwise distinct.
+    fn get_distinct_variable_groups(dv_pairs: &[(usize, usize)]) -> Vec<Vec<usize>> {
+        let is_dv = |a: usize, b: usize| dv_pairs.contains(&(a, b)) || dv_pairs.contains(&(b, a));
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for &(a, b) in dv_pairs {
+            if groups
+                .iter()
+                .any(|group| group.contains(&a) && group.contains(&b))
+            {
+                continue;
+            }
+            if let Some(group) = groups
+                .iter_mut()
+                .find(|group| group.contains(&a) && group.iter().all(|&v| v == a || is_dv(v, b)))
+            {
+                group.push(b);
+            } else if let Some(group) = groups
+                .iter_mut()
+                .find(|group| group.contains(&b) && group.iter().all(|&v| v == b || is_dv(v, a)))
+            {
+                group.push(a);
+            } else {
+                groups.push(vec![a, b]);
+            }
+        }
+        for group in groups.iter_mut() {
+            group.sort_unstable();
+        }
+        groups
+    }
+
+    /// Escapes a text segment of a comment,
+    /// highlighting its contribution and discouraged usage notes
+    fn render_contributions(&self, text: &str) -> String {
+        let mut output = String::new();
+        let mut last = 0;
+        for caps in self.contrib_regex.captures_iter(text) {
+            let note = caps.get(0).unwrap();
+            output.push_str(&self.render_discouraged(&text[last..note.start()]));
+            let contributor = caps
+                .get(2)
+                .expect("Contribution Regex did not return a contributor")
+                .as_str();
+            // The contributor pages are not part of the static export
+            let contributor = if self.exporting {
+                escape_html(contributor)
+            } else {
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_html(&contributor_link(
+                        &contributor
+                            .split_whitespace()
+                            .collect::<Vec<&str>>()
+                            .join(" ")
+                    )),
+                    escape_html(contributor)
+                )
+            };
+            output.push_str(&format!(
+                "<span class=\"contrib\">({} by {}, {})</span>",
+                escape_html(
+                    caps.get(1)
+                        .expect("Contribution Regex did not return a contribution type")
+                        .as_str()
+                ),
+                contributor,
+                escape_html(
+                    caps.get(3)
+                        .expect("Contribution Regex did not return a contribution date")
+                        .as_str()
+                ),
+            ));
+            last = note.end();
+        }
+        output.push_str(&self.render_discouraged(&text[last..]));
+        output
+    }
+
+    /// Escapes a text segment of a comment, highlighting its discouraged usage notes
+    fn render_discouraged(&self, text: &str) -> String {
+        self.discouraged_regex
+            .replace_all(&escape_html(text), |caps: &Captures| {
                 format!(
                     "<span class=\"discouraged\">{}</span>",
                     caps.get(0).unwrap().as_str(),
                 )
-            });
-        let comment = self.math_regex.replace_all(&comment, |caps: &Captures| {
-            format!(
-                "<span class=\"math\">{}</span>",
-                caps.get(1).unwrap().as_str()
-            )
-        });
-        let comment = self.link_regex.replace_all(&comment, |caps: &Captures| {
-            format!(
-                "<a href=\"{}\" class=\"label\">{}</a> ",
-                caps.get(1).map_or("#", |m| m.as_str()),
-                caps.get(1).map_or("-", |m| m.as_str())
-            )
-        });
-        let comment = comment.replace("~~", "~");
-        let comment = self
-            .underline_regex
-            .replace_all(&comment, |caps: &Captures| {
-                format!("<em>{}</em>", caps.get(1).unwrap().as_str(),)
-            });
-        let comment = self.bibl_regex.replace_all(&comment, |caps: &Captures| {
-            format!(
-                "<a href=\"{}#{}\">{}</a>",
-                self.bib_file,
-                caps.get(1).map_or("", |m| m.as_str()),
-                caps.get(1).map_or("", |m| m.as_str())
-            )
-        });
-        //            Double tildes ~~ shall be substituted with single tildes, see link in ~ dn1
-
-        //            Anything inside <HTML> shall be unchanged
-        //            _..._ -> to italics <em></em>, except if part of external hyperlinks
-        //            See mmwtex.c
-        comment.to_string()
+            })
+            .to_string()
     }
 
-    pub(crate) fn render_comment_new(&self, buf: &[u8], span: Span) -> String {
+    pub(crate) fn render_comment(
+        &self,
+        buf: &[u8],
+        span: Span,
+        expression_renderer: &ExpressionRenderer,
+    ) -> String {
         let mut parser = CommentParser::new(buf, span);
         let mut htmls = 0;
         let mut trim_prev_ws = true;
+        let mut math_tokens: Vec<String> = vec![];
         let mut comment = "".to_string();
         while let Some(item) = parser.next() {
             let mut out = vec![];
@@ -375,27 +675,28 @@ impl Renderer {
                             comment.push(' ');
                         }
                     }
-                    if htmls == 0 {
+                    if htmls > 0 {
                         comment.push_str(s);
                     } else {
-                        comment.push_str(
-                            &s.replace('&', "&amp;")
-                                .replace('<', "&lt;")
-                                .replace('>', "&gt;"),
-                        );
+                        comment.push_str(&self.render_contributions(s));
                     }
                 }
                 CommentItem::LineBreak(_) => {
                     trim_prev_ws = true;
                     comment.push_str("<p style=\"margin-bottom:0em\">");
                 }
-                // TODO : math mode: gather symbols, defer to expression renderer
-                CommentItem::StartMathMode(_) => {}
-                CommentItem::EndMathMode(_) => {}
+                CommentItem::StartMathMode(_) => math_tokens.clear(),
+                CommentItem::EndMathMode(_) => {
+                    trim_prev_ws = true;
+                    comment.push_str(&format!(
+                        "<span class=\"math\">{}</span>",
+                        expression_renderer.render_math(&math_tokens, &self.db)
+                    ));
+                }
                 CommentItem::MathToken(sp) => {
                     out.clear();
                     parser.unescape_math(sp, &mut out);
-                    // TODO! comment.push_str(self.html_defs[&*out]);
+                    math_tokens.push(as_str(&out).to_string());
                 }
                 CommentItem::Label(_, sp) => {
                     trim_prev_ws = true;
@@ -434,53 +735,155 @@ impl Renderer {
         comment
     }
 
-    pub fn render_statement(&self, explorer: String, label: String) -> Option<String> {
-        let sref = self.db.statement(label.as_bytes())?;
-        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
-
-        // Header
-        let header = expression_renderer.get_header();
+    /// The hypotheses of the given statement
+    pub(crate) fn get_hyps(
+        &self,
+        sref: &StatementRef,
+        expression_renderer: &ExpressionRenderer,
+        is_proof: bool,
+    ) -> Option<Vec<HypInfo>> {
+        Some(
+            self.db
+                .scope_result()
+                .get(sref.label())?
+                .as_ref(&self.db)
+                .essentials()
+                .map(|(label, formula)| HypInfo {
+                    label: as_str(self.db.name_result().atom_name(label)).to_string(),
+                    tokens: formula.as_ref(&self.db).to_string(),
+                    expr: expression_renderer
+                        .render_formula(formula, &self.db, is_proof)
+                        .unwrap_or_else(|e| e),
+                })
+                .collect(),
+        )
+    }
 
-        // Table of Contents - Breadcrumb - Prev and Next links
-        let nav = self.get_nav(&self.db.get_outline_node(sref));
+    /// The substitution unifying the assertion referenced by a proof step with the step's formula,
+    /// in the order of the variables' floating hypotheses
+    fn get_substitutions(
+        &self,
+        proof_tree: &ProofTreeArray,
+        tree_index: usize,
+        stmt: &StatementRef,
+        expression_renderer: &ExpressionRenderer,
+        use_provables: bool,
+    ) -> Vec<SubstitutionInfo> {
+        if !stmt.is_assertion() {
+            return vec![];
+        }
+        let nset = self.db.name_result();
+        let mut subst = Substitutions::new();
+        let unified =
+            ExpressionRenderer::as_formula(&self.db, proof_tree, tree_index, use_provables)
+                .and_then(|formula| {
+                    let assertion =
+                        expression_renderer.get_formula(stmt, &self.db, use_provables)?;
+                    formula
+                        .unify(&assertion, &mut subst)
+                        .map_err(|_| "Could not unify".to_string())
+                });
+        if unified.is_err() {
+            return vec![];
+        }
+        let mut substitutions = (&subst)
+            .into_iter()
+            .filter_map(|(label, subformula)| {
+                let variable = nset.var_atom(self.db.statement_by_label(*label)?)?;
+                Some((
+                    *label,
+                    SubstitutionInfo {
+                        variable: expression_renderer
+                            .render_variable(variable, &self.db)
+                            .unwrap_or_else(|_| as_str(nset.atom_name(variable)).to_string()),
+                        expr: expression_renderer.render_subformula(subformula, &self.db),
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+        substitutions.sort_by_key(|(label, _)| *label);
+        substitutions.into_iter().map(|(_, info)| info).collect()
+    }
 
-        // Comments
-        let comment = if let Some(cmt) = sref.associated_comment() {
-            let mut span = cmt.span();
-            span.start += 2;
-            span.end -= 3;
-            self.render_comment(&String::from_utf8_lossy(
-                span.as_ref(&cmt.segment().segment.buffer),
-            ))
-        } else {
-            "(This statement does not have an associated comment)".to_string()
-        };
+    /// A step of a proof or of a syntax breakdown.
+    /// Syntax steps, which construct `wff`s and `class`es, are rendered without the provable typecode.
+    fn get_step_info(
+        &self,
+        steps: &StepsContext,
+        cur: usize,
+        id: usize,
+        stmt: StatementRef,
+        hyps: &[usize],
+        syntax: bool,
+    ) -> StepInfo {
+        let StepsContext {
+            proof_tree,
+            expression_renderer,
+            substitutions,
+        } = *steps;
+        let tokens = String::from_utf8_lossy(&proof_tree.exprs().unwrap()[cur]).to_string();
+        StepInfo {
+            id: id.to_string(),
+            hyps: hyps.iter().map(usize::to_string).collect::<Vec<String>>(),
+            label: as_str(stmt.label()).to_string(),
+            r#type: Renderer::stmt_type(stmt),
+            link: stmt.is_assertion(),
+            syntax,
+            substitutions: if substitutions {
+                Some(self.get_substitutions(proof_tree, cur, &stmt, expression_renderer, !syntax))
+            } else {
+                None
+            },
+            expr: expression_renderer
+                .clone()
+                .render_expression(proof_tree, cur, !syntax)
+                .unwrap_or_else(|e| format!("Could not format {} : {}", tokens, e)),
+            tokens,
+        }
+    }
 
-        // Previous and next statements
-        // let _prev_label = if let Some(prev_sref) = self.db.prev_sref(sref) { &String::from_utf8_lossy(prev_sref.label()) } else { "" };
+    /// Whether the given statement is a syntax statement, i.e. its typecode is not the provable typecode
+    fn is_syntax(&self, stmt: &StatementRef) -> bool {
+        let nset = self.db.name_result();
+        stmt.math_iter().next().map_or(true, |typecode| {
+            nset.get_atom(&typecode) != self.db.grammar_result().provable_typecode()
+        })
+    }
 
-        // Proof or Syntax proof
-        let (is_proof, steps) = match sref.statement_type() {
+    /// The proof steps of a theorem, or the syntax breakdown of other statements.
+    /// The syntax steps of proofs are only included if `all_steps` is set,
+    /// like metamath.exe's `/all` proof display.
+    /// The substitutions of each step are only computed if `substitutions` is set.
+    /// Also returns whether this is a proof.
+    pub(crate) fn get_steps(
+        &self,
+        sref: &StatementRef,
+        expression_renderer: &ExpressionRenderer,
+        all_steps: bool,
+        substitutions: bool,
+    ) -> (bool, Vec<StepInfo>) {
+        match sref.statement_type() {
             StatementType::Provable => (
                 true,
-                match self.db.get_proof_tree(sref) {
+                match self.db.get_proof_tree(*sref) {
+                    Some(proof_tree) if all_steps => {
+                        let steps = StepsContext {
+                            proof_tree: &proof_tree,
+                            expression_renderer,
+                            substitutions,
+                        };
+                        proof_tree.with_steps(&self.db, |cur, stmt, hyps| {
+                            self.get_step_info(&steps, cur, cur, stmt, hyps, self.is_syntax(&stmt))
+                        })
+                    }
                     Some(proof_tree) => {
-                        proof_tree.with_logical_steps(&self.db, |cur, ix, stmt, hyps| StepInfo {
-                            id: ix.to_string(),
-                            hyps: hyps.iter().map(usize::to_string).collect::<Vec<String>>(),
-                            label: as_str(stmt.label()).to_string(),
-                            r#type: Renderer::stmt_type(stmt),
-                            link: stmt.is_assertion(),
-                            expr: expression_renderer
-                                .clone()
-                                .render_expression(&proof_tree, cur, true)
-                                .unwrap_or_else(|e| {
-                                    format!(
-                                        "Could not format {} : {}",
-                                        &String::from_utf8_lossy(&proof_tree.exprs().unwrap()[cur]),
-                                        e
-                                    )
-                                }),
+                        let steps = StepsContext {
+                            proof_tree: &proof_tree,
+                            expression_renderer,
+                            substitutions,
+                        };
+                        proof_tree.with_logical_steps(&self.db, |cur, ix, stmt, hyps| {
+                            self.get_step_info(&steps, cur, ix, stmt, hyps, false)
                         })
                     }
                     None => vec![],
@@ -488,63 +891,141 @@ impl Renderer {
             ),
             StatementType::Axiom | StatementType::Essential | StatementType::Floating => (
                 false,
-                match self.db.stmt_parse_result().get_formula(&sref) {
+                match self.db.stmt_parse_result().get_formula(sref) {
                     Some(formula) => {
                         let proof_tree = self.db.get_syntax_proof_tree(formula);
-                        proof_tree.with_steps(&self.db, |cur, stmt, hyps| StepInfo {
-                            id: cur.to_string(),
-                            hyps: hyps.iter().map(usize::to_string).collect::<Vec<String>>(),
-                            label: as_str(stmt.label()).to_string(),
-                            r#type: Renderer::stmt_type(stmt),
-                            link: stmt.is_assertion(),
-                            expr: expression_renderer
-                                .clone()
-                                .render_expression(&proof_tree, cur, false)
-                                .unwrap_or_else(|e| {
-                                    format!(
-                                        "Could not format {} : {}",
-                                        &String::from_utf8_lossy(&proof_tree.exprs().unwrap()[cur]),
-                                        e
-                                    )
-                                }),
+                        let steps = StepsContext {
+                            proof_tree: &proof_tree,
+                            expression_renderer,
+                            substitutions,
+                        };
+                        proof_tree.with_steps(&self.db, |cur, stmt, hyps| {
+                            self.get_step_info(&steps, cur, cur, stmt, hyps, true)
                         })
                     }
                     None => vec![],
                 },
             ),
             _ => (false, vec![]),
-        };
+        }
+    }
 
-        // Statement type
-        let statement_type = if is_proof {
+    /// The distinct variable groups of the given statement
+    pub(crate) fn get_distinct_variables(&self, sref: &StatementRef) -> Option<Vec<Vec<Atom>>> {
+        let frame = self.db.scope_result().get(sref.label())?;
+        Some(
+            Renderer::get_distinct_variable_groups(&frame.mandatory_dv)
+                .iter()
+                .map(|group| group.iter().map(|&index| frame.var_list[index]).collect())
+                .collect(),
+        )
+    }
+
+    pub(crate) fn render_distinct_variables(
+        &self,
+        group: &[Atom],
+        expression_renderer: &ExpressionRenderer,
+    ) -> String {
+        group
+            .iter()
+            .map(|&atom| {
+                expression_renderer
+                    .render_variable(atom, &self.db)
+                    .unwrap_or_else(|e| e)
+            })
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    pub(crate) fn get_statement_type(label: &str, is_proof: bool, no_steps: bool) -> String {
+        if is_proof {
             "Theorem".to_string()
-        } else if steps.is_empty() {
+        } else if no_steps {
             "Syntax definition".to_string()
         } else if label.starts_with("df-") {
             "Definition".to_string()
         } else {
             "Axiom".to_string()
+        }
+    }
+
+    pub fn render_statement(
+        &self,
+        explorer: String,
+        label: String,
+        tree: bool,
+        all_steps: bool,
+    ) -> Option<String> {
+        let sref = self.db.statement(label.as_bytes())?;
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+
+        // Header
+        let header = expression_renderer.get_header();
+
+        // Table of Contents - Breadcrumb - Prev and Next links
+        let nav = self.get_nav(&self.db.get_outline_node(sref));
+
+        // Comments
+        let comment = if let Some(cmt) = sref.associated_comment() {
+            let mut span = cmt.span();
+            span.start += 2;
+            span.end -= 3;
+            self.render_comment(&cmt.segment().segment.buffer, span, &expression_renderer)
+        } else {
+            "(This statement does not have an associated comment)".to_string()
         };
 
+        // Proof or Syntax proof
+        let (is_proof, steps) = self.get_steps(&sref, &expression_renderer, all_steps, false);
+
+        // Statement type
+        let statement_type = Renderer::get_statement_type(&label, is_proof, steps.is_empty());
+
         // Statement assertion
         let expr = expression_renderer
             .render_statement(&sref, &self.db, is_proof)
             .unwrap_or_else(|e| format!("Could not format assertion : {}", e));
 
         // Hypotheses
-        let hyps = self
-            .db
-            .scope_result()
-            .get(sref.label())?
-            .as_ref(&self.db)
-            .essentials()
-            .map(|(label, formula)| HypInfo {
-                label: as_str(self.db.name_result().atom_name(label)).to_string(),
-                expr: expression_renderer
-                    .render_formula(formula, &self.db, is_proof)
-                    .unwrap_or_else(|e| e),
-            })
+        let hyps = self.get_hyps(&sref, &expression_renderer, is_proof)?;
+
+        // Distinct variables
+        let dvs = self
+            .get_distinct_variables(&sref)?
+            .iter()
+            .map(|group| self.render_distinct_variables(group, &expression_renderer))
+            .collect();
+
+        // Theorems referencing this statement
+        let uses = self.get_uses(&sref);
+
+        // Axioms and definitions this theorem depends on
+        let dependencies = self.get_dependencies(&sref);
+
+        // Colors of the variables used in the page
+        let statement_tokens: Vec<String> = sref
+            .math_iter()
+            .map(|token| as_str(&token).to_string())
             .collect();
+        let legend = expression_renderer
+            .variable_colors()
+            .map_or(vec![], |colors| {
+                colors.legend(
+                    &self.db,
+                    statement_tokens
+                        .iter()
+                        .map(String::as_str)
+                        .chain(hyps.iter().flat_map(|hyp| hyp.tokens.split_whitespace()))
+                        .chain(steps.iter().flat_map(|step| step.tokens.split_whitespace())),
+                )
+            });
+
+        // Proof tree view
+        let tree = if tree {
+            get_proof_trees(&steps)
+        } else {
+            vec![]
+        };
 
         let info = PageInfo {
             header,
@@ -555,8 +1036,15 @@ impl Renderer {
             comment,
             expr,
             hyps,
+            dvs,
             is_proof,
             steps,
+            all_steps,
+            tree,
+            uses,
+            axioms: dependencies.axioms,
+            definitions: dependencies.definitions,
+            legend,
         };
         Some(
             self.templates
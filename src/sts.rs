@@ -1,7 +1,10 @@
+use crate::colors::VariableColors;
+use crate::config::MathJax;
 use metamath_knife::formula::Label;
 use metamath_knife::formula::Substitutions;
 use metamath_knife::formula::TypeCode;
 use metamath_knife::grammar::FormulaToken;
+use metamath_knife::nameck::Atom;
 use metamath_knife::statement::as_str;
 use metamath_knife::Database;
 use metamath_knife::Formula;
@@ -9,6 +12,10 @@ use metamath_knife::StatementType;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Header loading MathJax from the local `static` directory
+const LOCAL_MATHJAX_HEADER: &str =
+    "<script id=\"MathJax-script\" async src=\"/static/mathjax/tex-mml-chtml.js\"></script>";
+
 #[derive(Clone, Debug)]
 pub struct StsScheme {
     is_identifier: bool,
@@ -35,17 +42,19 @@ pub struct StsDefinition {
     identifiers: Arc<HashMap<Label, TypeCode>>,
     pub(crate) header: String,
     display: String,
-    _inline: String,
+    inline: String,
     _command: String,
+    /// The colours of the variables, only for the MathML format
+    pub(crate) colors: Option<VariableColors>,
 }
 
 impl StsDefinition {
     pub fn new(
         database: Database,
         schemes_list: Vec<StsScheme>,
-        _header: String,
+        header: String,
         display: String,
-        _inline: String,
+        inline: String,
         _command: String,
     ) -> Result<Self, String> {
         let mut schemes = HashMap::new();
@@ -65,23 +74,58 @@ impl StsDefinition {
         }
         let schemes = Arc::new(schemes);
         let identifiers = Arc::new(identifiers);
-        let header = "<script src=\"https://cdnjs.cloudflare.com/polyfill/v3/polyfill.min.js?version=4.8.0&features=es6\"></script>
-    		<script id=\"MathJax-script\" async src=\"https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-mml-chtml.js\"></script>".to_string();
         Ok(Self {
             database,
             schemes,
             identifiers,
             header,
             display,
-            _inline,
+            inline,
             _command,
+            colors: None,
         })
     }
 
+    /// This definition, with the header replaced according to where MathJax shall be loaded from
+    pub(crate) fn with_mathjax(&self, mathjax: MathJax) -> Self {
+        let header = match mathjax {
+            MathJax::Header => self.header.clone(),
+            MathJax::Local => LOCAL_MATHJAX_HEADER.to_string(),
+            MathJax::Native => "".to_string(),
+        };
+        Self {
+            header,
+            ..self.clone()
+        }
+    }
+
+    /// This definition, colouring the variables with the given colours
+    pub(crate) fn with_colors(self, colors: VariableColors) -> Self {
+        Self {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    /// Colours the MathML of the variable of the given floating hypothesis
+    fn color_variable(&self, label: Label, mathml: String) -> String {
+        let color = self.colors.as_ref().and_then(|colors| {
+            let sref = self.database.statement_by_label(label)?;
+            colors.color(self.database.name_result().var_atom(sref)?)
+        });
+        match color {
+            Some(color) => format!("<mstyle mathcolor=\"{}\">{}</mstyle>", color, mathml),
+            None => mathml,
+        }
+    }
+
     fn apply_scheme(&self, scheme: &StsScheme, formula: &Formula) -> Option<String> {
         let nset = self.database.name_result();
         if scheme.is_identifier {
-            (&scheme.formula == formula).then(|| scheme.subst.clone())
+            (&scheme.formula == formula).then(|| match formula.get_by_path(&[]) {
+                Some(label) => self.color_variable(label, scheme.subst.clone()),
+                None => scheme.subst.clone(),
+            })
         } else {
             let mut subst = Substitutions::new();
             formula
@@ -142,6 +186,46 @@ impl StsDefinition {
         Ok(display.replace("###", &mathml))
     }
 
+    /// Format a single variable, using its identifier scheme
+    pub fn render_variable(&self, atom: Atom) -> Result<String, String> {
+        let nset = self.database.name_result();
+        let scheme = self
+            .schemes
+            .values()
+            .flatten()
+            .find(|scheme| {
+                scheme.is_identifier
+                    && scheme
+                        .formula
+                        .get_by_path(&[])
+                        .and_then(|label| self.database.statement_by_label(label))
+                        .and_then(|sref| nset.var_atom(sref))
+                        == Some(atom)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No typesetting found for variable {}",
+                    as_str(nset.atom_name(atom))
+                )
+            })?;
+        let mathml = match scheme.formula.get_by_path(&[]) {
+            Some(label) => self.color_variable(label, scheme.subst.clone()),
+            None => scheme.subst.clone(),
+        };
+        Ok(self.display.replace("###", &mathml))
+    }
+
+    /// Format a formula within text, using the inline template
+    pub fn render_inline(&self, formula: &Formula, use_provables: bool) -> Result<String, String> {
+        let typecode = if use_provables {
+            self.database.grammar_result().provable_typecode()
+        } else {
+            formula.get_typecode()
+        };
+        let mathml = self.format(typecode, formula)?;
+        Ok(self.inline.replace("###", &mathml))
+    }
+
     pub fn check(&self) {
         let provable = self.database.grammar_result().provable_typecode();
         let nset = self.database.name_result();
@@ -1,5 +1,4 @@
 use crate::sts::{StsDefinition, StsScheme};
-use clap::ArgMatches;
 use metamath_knife::formula::TypeCode;
 use metamath_knife::{grammar::FormulaToken, statement::as_str};
 use metamath_knife::{Database, Span};
@@ -8,8 +7,8 @@ use nom::{
     character::complete::multispace1, combinator::map, combinator::value, multi::many0,
     multi::separated_list1, sequence::delimited, sequence::terminated, sequence::tuple, IResult,
 };
-use regex::Regex;
 use std::fs::read_to_string;
+use std::path::Path;
 
 impl StsScheme {
     fn parse(
@@ -171,21 +170,20 @@ impl StsDefinition {
     }
 }
 
-pub fn parse_sts(db: Database, args: &ArgMatches, format: &str) -> Result<StsDefinition, String> {
-    let dbpath = args.value_of("database").unwrap();
-    // Match an optional path ending in /, the database name, and the .mm extention
-    let dbname_matches = Regex::new(r"^(.+/)?([^/]+)\.mm$")
-        .unwrap()
-        .captures(dbpath)
-        .ok_or("Could not parse database file name")?;
-    let path = dbname_matches.get(1).map_or("", |m| m.as_str());
-    let name = dbname_matches.get(2).unwrap().as_str();
-    let filename = format!("{}{}-{}.mmts", path, name, format);
-    let contents =
-        read_to_string(filename).expect("Something went wrong reading the STS definition file");
+/// Parses the given STS definition file, if it exists
+pub fn parse_sts(
+    db: Database,
+    filename: &str,
+    check_sts: bool,
+) -> Result<Option<StsDefinition>, String> {
+    if !Path::new(filename).exists() {
+        return Ok(None);
+    }
+    let contents = read_to_string(filename)
+        .map_err(|e| format!("Could not read STS definition file {}: {}", filename, e))?;
     let definition = StsDefinition::parse(db, contents)?;
-    if args.is_present("check_sts") {
+    if check_sts {
         definition.check();
     }
-    Ok(definition)
+    Ok(Some(definition))
 }
@@ -0,0 +1,216 @@
+//! Paginated list of all assertions, the equivalent of metamath.exe's `mmtheorems` pages
+use crate::colors::VarColorInfo;
+use crate::statement::ExpressionRenderer;
+use crate::statement::Renderer;
+use crate::toc::NavInfo;
+use metamath_knife::outline::OutlineNodeRef;
+use metamath_knife::parser::HeadingLevel;
+use metamath_knife::statement::as_str;
+use metamath_knife::statement::StatementRef;
+use metamath_knife::Database;
+use metamath_knife::StatementType;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Number of assertions listed on each page
+pub(crate) const THEOREMS_PER_PAGE: usize = 100;
+
+#[derive(Clone, Serialize)]
+struct HeadingInfo {
+    name: String,
+    chapter_ref: usize,
+}
+
+/// All the assertions of the database, in database order,
+/// together with the outline headings starting on each page.
+#[derive(Clone, Default)]
+pub(crate) struct TheoremList {
+    labels: Arc<Vec<String>>,
+    headings: Arc<Vec<Vec<HeadingInfo>>>,
+}
+
+#[derive(Serialize)]
+struct TheoremInfo {
+    number: usize,
+    label: String,
+    expr: String,
+    description: String,
+}
+
+#[derive(Serialize)]
+struct PageLinkInfo {
+    page: usize,
+    title: String,
+    current: bool,
+}
+
+#[derive(Serialize)]
+struct TheoremsPageInfo {
+    header: String,
+    explorer: String,
+    nav: NavInfo,
+    link: String,
+    page: usize,
+    page_count: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+    pages: Vec<PageLinkInfo>,
+    headings: Vec<HeadingInfo>,
+    theorems: Vec<TheoremInfo>,
+    legend: Vec<VarColorInfo>,
+}
+
+impl TheoremList {
+    pub(crate) fn new(database: &Database) -> Self {
+        let nset = database.name_result();
+        let provable = database.grammar_result().provable_typecode();
+        let mut labels = vec![];
+        let mut headings: Vec<Vec<HeadingInfo>> = vec![];
+        let mut last_heading = None;
+        for sref in database.statements() {
+            if !matches!(
+                sref.statement_type(),
+                StatementType::Axiom | StatementType::Provable
+            ) || sref
+                .math_iter()
+                .next()
+                .map_or(true, |typecode| nset.get_atom(&typecode) != provable)
+            {
+                continue;
+            }
+            if labels.len() % THEOREMS_PER_PAGE == 0 {
+                headings.push(vec![]);
+            }
+            if let Some(heading) = TheoremList::get_heading(database.get_outline_node(sref)) {
+                if last_heading != Some(heading.get_ref()) {
+                    last_heading = Some(heading.get_ref());
+                    headings.last_mut().unwrap().push(HeadingInfo {
+                        name: heading.get_name().to_string(),
+                        chapter_ref: heading.get_ref(),
+                    });
+                }
+            }
+            labels.push(as_str(sref.label()).to_string());
+        }
+        Self {
+            labels: Arc::new(labels),
+            headings: Arc::new(headings),
+        }
+    }
+
+    /// The closest heading containing the given outline node
+    pub(crate) fn get_heading(node: OutlineNodeRef) -> Option<OutlineNodeRef> {
+        if node.get_level() == HeadingLevel::Statement {
+            node.parent()
+        } else {
+            Some(node)
+        }
+    }
+
+    fn page_count(&self) -> usize {
+        (self.labels.len() + THEOREMS_PER_PAGE - 1) / THEOREMS_PER_PAGE
+    }
+}
+
+impl Renderer {
+    /// Renders the first sentence of the description of a statement
+    pub(crate) fn render_description_summary(
+        &self,
+        sref: &StatementRef,
+        expression_renderer: &ExpressionRenderer,
+    ) -> String {
+        let cmt = match sref.associated_comment() {
+            Some(cmt) => cmt,
+            None => return "".to_string(),
+        };
+        let buf = &cmt.segment().segment.buffer;
+        let mut span = cmt.span();
+        span.start += 2;
+        span.end -= 3;
+        let text = span.as_ref(buf);
+        let mut in_math = false;
+        let mut i = 0;
+        while i < text.len() {
+            match text[i] {
+                b'`' if text.get(i + 1) == Some(&b'`') => i += 1,
+                b'`' => in_math = !in_math,
+                b'.' if !in_math && text.get(i + 1).map_or(true, |c| c.is_ascii_whitespace()) => {
+                    span.end = span.start + i as u32 + 1;
+                    break;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        self.render_comment(buf, span, expression_renderer)
+    }
+
+    pub fn render_theorems(&self, explorer: String, page: usize) -> Option<String> {
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let page_count = self.theorems.page_count();
+        if page == 0 || page > page_count {
+            return None;
+        }
+        let first = (page - 1) * THEOREMS_PER_PAGE;
+        let theorems: Vec<TheoremInfo> = self
+            .theorems
+            .labels
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(THEOREMS_PER_PAGE)
+            .filter_map(|(index, label)| {
+                let sref = self.db.statement(label.as_bytes())?;
+                let expr = expression_renderer
+                    .render_statement(
+                        &sref,
+                        &self.db,
+                        sref.statement_type() == StatementType::Provable,
+                    )
+                    .unwrap_or_else(|e| format!("Could not format assertion : {}", e));
+                Some(TheoremInfo {
+                    number: index + 1,
+                    label: label.clone(),
+                    expr,
+                    description: self.render_description_summary(&sref, &expression_renderer),
+                })
+            })
+            .collect();
+        let pages = self
+            .theorems
+            .headings
+            .iter()
+            .enumerate()
+            .map(|(index, headings)| PageLinkInfo {
+                page: index + 1,
+                title: headings
+                    .first()
+                    .map_or("".to_string(), |heading| heading.name.clone()),
+                current: index + 1 == page,
+            })
+            .collect();
+        let legend = self.get_legend(
+            &expression_renderer,
+            theorems.iter().map(|theorem| theorem.label.as_str()),
+        );
+        let info = TheoremsPageInfo {
+            header: expression_renderer.get_header(),
+            nav: self.get_root_nav(),
+            explorer,
+            link: format!("theorems?page={}", page),
+            page,
+            page_count,
+            prev: (page > 1).then(|| page - 1),
+            next: (page < page_count).then(|| page + 1),
+            pages,
+            headings: self.theorems.headings[page - 1].clone(),
+            theorems,
+            legend,
+        };
+        Some(
+            self.templates
+                .render("theorems", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
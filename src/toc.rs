@@ -1,18 +1,24 @@
+use crate::colors::VarColorInfo;
+use crate::statement::DatabaseInfo;
+use crate::statement::ExpressionRenderer;
 use crate::statement::Renderer;
 use crate::statement::TypesettingInfo;
 use metamath_knife::outline::OutlineNodeRef;
 use metamath_knife::parser::HeadingLevel;
+use metamath_knife::statement::StatementType;
 use serde::Serialize;
 use serde::Serializer;
 
 #[derive(Serialize)]
 pub(crate) struct TocInfo {
+    header: String,
     nav: NavInfo,
     name: String,
     comment: Option<String>,
     explorer: String,
     link: LinkInfo,
     children: Vec<ChapterInfo>,
+    legend: Vec<VarColorInfo>,
 }
 
 #[derive(Serialize)]
@@ -22,16 +28,30 @@ pub(crate) struct ChapterInfo {
     stmt_level: bool,
     children: Vec<ChapterInfo>,
     index: Option<usize>,
+    /// For statements, the rendered assertion
+    expr: Option<String>,
+    /// For statements, the first sentence of the description
+    description: Option<String>,
 }
 
 #[derive(Serialize)]
 pub(crate) struct NavInfo {
     breadcrumb: Vec<ChapterInfo>,
     next: Option<ChapterInfo>,
+    prev: Option<ChapterInfo>,
+    up: Option<ChapterInfo>,
+    first: Option<ChapterInfo>,
+    last: Option<ChapterInfo>,
     typesettings: Vec<TypesettingInfo>,
+    database: DatabaseInfo,
+    databases: Vec<DatabaseInfo>,
+    /// Whether the page is part of the static export, where the dynamic pages are not available
+    export: bool,
+    /// The extension appended to the links to statement and table of contents pages
+    suffix: &'static str,
 }
 
-enum LinkInfo {
+pub(crate) enum LinkInfo {
     Toc,
     ChapterRef(String),
     StatementRef(String),
@@ -50,12 +70,24 @@ impl From<&OutlineNodeRef<'_>> for LinkInfo {
 impl From<&(Option<usize>, OutlineNodeRef<'_>)> for ChapterInfo {
     fn from(data: &(Option<usize>, OutlineNodeRef<'_>)) -> Self {
         let &(index, ref node) = data;
+        ChapterInfo::with_index(node, index)
+    }
+}
+
+impl ChapterInfo {
+    fn of(node: &OutlineNodeRef<'_>) -> Self {
+        ChapterInfo::with_index(node, None)
+    }
+
+    fn with_index(node: &OutlineNodeRef<'_>, index: Option<usize>) -> Self {
         ChapterInfo {
             name: node.get_name().to_string(),
             index,
             stmt_level: node.get_level() == HeadingLevel::Statement,
             link: node.into(),
             children: vec![],
+            expr: None,
+            description: None,
         }
     }
 }
@@ -69,7 +101,7 @@ impl Serialize for LinkInfo {
             LinkInfo::Toc => serializer.serialize_str("toc"),
             LinkInfo::StatementRef(name) => serializer.serialize_str(name),
             LinkInfo::ChapterRef(chapter_ref) => {
-                serializer.serialize_str(&format!("toc?ref={}", chapter_ref))
+                serializer.serialize_str(&format!("toc-{}", chapter_ref))
             }
         }
     }
@@ -77,10 +109,61 @@ impl Serialize for LinkInfo {
 
 impl Renderer {
     pub(crate) fn get_nav(&self, node: &OutlineNodeRef) -> NavInfo {
+        let siblings: Vec<OutlineNodeRef> = node
+            .parent()
+            .map_or(vec![], |parent| parent.children_iter().collect());
+        let position = siblings.iter().position(|sibling| {
+            sibling.get_statement().address() == node.get_statement().address()
+        });
+        let (first, last) = match position {
+            Some(position) => (
+                siblings
+                    .first()
+                    .filter(|_| position > 0)
+                    .map(ChapterInfo::of),
+                siblings
+                    .last()
+                    .filter(|_| position + 1 < siblings.len())
+                    .map(ChapterInfo::of),
+            ),
+            None => (None, None),
+        };
+        // The previous node in database order: the last descendant of the previous sibling, or the parent
+        let prev = match position {
+            Some(position) if position > 0 => {
+                let mut prev = siblings.into_iter().nth(position - 1).unwrap();
+                loop {
+                    let last_child = prev.children_iter().last();
+                    match last_child {
+                        Some(last_child) => prev = last_child,
+                        None => break,
+                    }
+                }
+                Some(ChapterInfo::of(&prev))
+            }
+            _ => node.parent().map(|n| ChapterInfo::of(&n)),
+        };
         NavInfo {
             breadcrumb: self.get_breadcrumb(node),
             next: node.next().map(|n| (&(None, n)).into()),
-            typesettings: Renderer::get_typesettings(),
+            prev,
+            up: node.parent().map(|n| ChapterInfo::of(&n)),
+            first,
+            last,
+            typesettings: self.get_typesettings(),
+            database: self.database.clone(),
+            databases: self.databases.clone(),
+            export: self.exporting,
+            suffix: if self.exporting { ".html" } else { "" },
+        }
+    }
+
+    /// Navigation for the pages which are not part of the outline, like the search or the contributors:
+    /// only the breadcrumb of the root, without any sequence
+    pub(crate) fn get_root_nav(&self) -> NavInfo {
+        NavInfo {
+            next: None,
+            ..self.get_nav(&self.db.root_outline_node())
         }
     }
 
@@ -105,37 +188,82 @@ impl Renderer {
         })
     }
 
-    fn get_comment(&self, node: &OutlineNodeRef) -> Option<String> {
+    fn get_comment(
+        &self,
+        node: &OutlineNodeRef,
+        expression_renderer: &ExpressionRenderer,
+    ) -> Option<String> {
         let stmt = node.get_statement();
-        Some(self.render_comment_new(
+        Some(self.render_comment(
             &stmt.segment().segment.buffer,
             stmt.as_heading_comment()?.content,
+            expression_renderer,
         ))
     }
 
+    /// The information about a child node of a chapter, with the assertion and description of statements
+    fn get_chapter_info(
+        &self,
+        node: OutlineNodeRef,
+        expression_renderer: &ExpressionRenderer,
+    ) -> ChapterInfo {
+        let mut info: ChapterInfo = (&(None, node)).into();
+        if info.stmt_level {
+            if let Some(sref) = self.db.statement(info.name.as_bytes()) {
+                info.expr = Some(
+                    expression_renderer
+                        .render_statement(
+                            &sref,
+                            &self.db,
+                            sref.statement_type() == StatementType::Provable,
+                        )
+                        .unwrap_or_else(|e| format!("Could not format assertion : {}", e)),
+                );
+                info.description =
+                    Some(self.render_description_summary(&sref, expression_renderer));
+            }
+        }
+        info
+    }
+
     pub fn render_toc(&self, explorer: String, chapter_ref: usize) -> Option<String> {
         let node = if chapter_ref == 0 {
             self.db.root_outline_node()
         } else {
             self.db.get_outline_node_by_ref(chapter_ref)
         };
-        let comment = self.get_comment(&node);
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let comment = self.get_comment(&node, &expression_renderer);
+        let children: Vec<ChapterInfo> = node
+            .children_iter()
+            .map(|n| {
+                let children = n
+                    .children_iter()
+                    .map(|c| self.get_chapter_info(c, &expression_renderer))
+                    .collect();
+                ChapterInfo {
+                    children,
+                    ..self.get_chapter_info(n, &expression_renderer)
+                }
+            })
+            .collect();
+        let legend = self.get_legend(
+            &expression_renderer,
+            children
+                .iter()
+                .flat_map(|chapter| std::iter::once(chapter).chain(chapter.children.iter()))
+                .filter(|chapter| chapter.stmt_level)
+                .map(|chapter| chapter.name.as_str()),
+        );
         let info = TocInfo {
+            header: expression_renderer.get_header(),
             nav: self.get_nav(&node),
             explorer,
             name: node.get_name().to_string(),
             comment,
             link: (&node).into(),
-            children: node
-                .children_iter()
-                .map(|n| ChapterInfo {
-                    name: n.get_name().to_string(),
-                    index: None,
-                    link: (&n).into(),
-                    stmt_level: n.get_level() == HeadingLevel::Statement,
-                    children: n.children_iter().map(|c| (&(None, c)).into()).collect(),
-                })
-                .collect(),
+            children,
+            legend,
         };
         Some(
             self.templates
@@ -1,4 +1,6 @@
 //! Unicode Typesetting
+use crate::colors::var_color_classes;
+use crate::colors::VariableColors;
 use metamath_knife::formula::Formula;
 use metamath_knife::statement::as_str;
 use metamath_knife::statement::StatementRef;
@@ -8,52 +10,110 @@ use std::fmt::Write;
 #[derive(Clone)]
 pub(crate) struct UnicodeRenderer {
     pub(crate) database: Database,
+    /// The `htmlcss` of the database, and the styles of the `htmlvarcolor` classes
+    header: String,
+    /// Opening tags of the symbols, with the `htmlfont` of the database
+    open: String,
+    /// Closing tags of the symbols
+    close: String,
+    pub(crate) colors: VariableColors,
 }
 
 impl UnicodeRenderer {
+    pub(crate) fn new(database: Database) -> Self {
+        let (header, font) = {
+            let typesetting = database.typesetting_result();
+            let mut header = typesetting
+                .html_css
+                .as_ref()
+                .map_or("".to_string(), |css| as_str(css).to_string());
+            // Style the variable classes used in `althtmldef`, even without `htmlcss`
+            let styles = var_color_classes(&database)
+                .iter()
+                .map(|(class, style)| format!(".uni .{} {{ {} }}", class, style))
+                .collect::<Vec<String>>()
+                .join("\n");
+            if !styles.is_empty() {
+                write!(header, "\n<style>\n{}\n</style>", styles).unwrap();
+            }
+            let font = typesetting
+                .html_font
+                .as_ref()
+                .map(|font| as_str(font).to_string());
+            (header, font)
+        };
+        let (open, close) = match font {
+            Some(font) if !font.is_empty() => (
+                format!("<span class=\"uni\"><span {}>", font),
+                "</span></span>".to_string(),
+            ),
+            _ => ("<span class=\"uni\">".to_string(), "</span>".to_string()),
+        };
+        let colors = VariableColors::new(&database);
+        UnicodeRenderer {
+            database,
+            header,
+            open,
+            close,
+            colors,
+        }
+    }
+
+    /// Renders a single symbol, coloured according to its typecode if it is a variable
+    fn render_token(&self, token: &[u8]) -> Result<String, String> {
+        let typesetting = self.database.typesetting_result();
+        let def = as_str(
+            typesetting
+                .get_alt_html_def(token)
+                .ok_or(format!("Unknown symbol: {}", as_str(token)))?,
+        );
+        let atom = self.database.name_result().get_atom(token);
+        Ok(match self.colors.color(atom) {
+            Some(color) => format!("<span style=\"color:{}\">{}</span>", color, def),
+            None => def.to_string(),
+        })
+    }
+
     pub(crate) fn get_header(&self) -> String {
-        //        self.database.typesetting_result().html_css.as_ref().map_or("", |t| as_str(&t)).to_string()
-        "".into()
+        self.header.clone()
     }
 
     pub(crate) fn render_formula(&self, formula: &Formula) -> Result<String, String> {
-        let mut output: String = "<span class=\"uni\"><span color=\"gray\">⊢</span> ".into();
+        let mut output = self.open.clone();
+        write!(output, "<span color=\"gray\">⊢</span> ").unwrap();
         //        write!(output, "{} ", as_str(if use_provables
-        let typesetting = self.database.typesetting_result();
         let nset = self.database.name_result();
         for symbol in formula.as_ref(&self.database).into_iter() {
-            let token = nset.atom_name(symbol);
-            write!(
-                output,
-                "{} ",
-                as_str(
-                    typesetting
-                        .get_alt_html_def(token)
-                        .ok_or(format!("Unknown symbol: {}", as_str(token)).to_string())?
-                )
-            )
-            .unwrap();
+            write!(output, "{} ", self.render_token(nset.atom_name(symbol))?).unwrap();
         }
-        write!(output, "</span>").unwrap();
+        write!(output, "{}", self.close).unwrap();
         Ok(output)
     }
 
+    pub(crate) fn render_symbols(&self, tokens: &[String]) -> Result<String, String> {
+        let mut output = self.open.clone();
+        for token in tokens {
+            write!(output, "{} ", self.render_token(token.as_bytes())?).unwrap();
+        }
+        write!(output, "{}", self.close).unwrap();
+        Ok(output)
+    }
+
+    pub(crate) fn render_symbol(&self, token: &[u8]) -> Result<String, String> {
+        Ok(format!(
+            "{}{}{}",
+            self.open,
+            self.render_token(token)?,
+            self.close
+        ))
+    }
+
     pub(crate) fn render_statement(&self, sref: &StatementRef) -> Result<String, String> {
-        let mut output: String = "<span class=\"uni\">".into();
-        let typesetting = self.database.typesetting_result();
+        let mut output = self.open.clone();
         for token in sref.math_iter() {
-            write!(
-                output,
-                "{} ",
-                as_str(
-                    typesetting
-                        .get_alt_html_def(&token)
-                        .ok_or(format!("Unknown symbol: {}", as_str(&token)).to_string())?
-                )
-            )
-            .unwrap();
+            write!(output, "{} ", self.render_token(&token)?).unwrap();
         }
-        write!(output, "</span>").unwrap();
+        write!(output, "{}", self.close).unwrap();
         Ok(output)
     }
 }
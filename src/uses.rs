@@ -0,0 +1,141 @@
+//! Reverse dependencies: which theorems reference a given statement in their proofs
+use crate::colors::VarColorInfo;
+use crate::statement::Renderer;
+use crate::toc::NavInfo;
+use metamath_knife::formula::Label;
+use metamath_knife::statement::as_str;
+use metamath_knife::statement::StatementRef;
+use metamath_knife::Database;
+use metamath_knife::StatementType;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Clone, Default)]
+pub(crate) struct UsesIndex {
+    uses: Arc<HashMap<Label, Vec<Label>>>,
+}
+
+#[derive(Serialize)]
+struct UseInfo {
+    label: String,
+    expr: String,
+}
+
+#[derive(Serialize)]
+struct UsesPageInfo {
+    header: String,
+    explorer: String,
+    label: String,
+    link: String,
+    nav: NavInfo,
+    uses: Vec<UseInfo>,
+    legend: Vec<VarColorInfo>,
+}
+
+impl UsesIndex {
+    /// Builds the index by scanning the labels referenced in every proof of the database.
+    pub(crate) fn new(database: &Database) -> Self {
+        let nset = database.name_result();
+        let mut uses: HashMap<Label, Vec<Label>> = HashMap::new();
+        for sref in database.statements() {
+            if sref.statement_type() != StatementType::Provable {
+                continue;
+            }
+            let user = match nset.lookup_label(sref.label()) {
+                Some(lookup) => lookup.atom,
+                None => continue,
+            };
+            let mut used: Vec<Label> = UsesIndex::proof_labels(&sref)
+                .into_iter()
+                .filter_map(|token| nset.lookup_label(token))
+                .map(|lookup| lookup.atom)
+                .filter(|&label| {
+                    database
+                        .statement_by_label(label)
+                        .map_or(false, |used_sref| used_sref.is_assertion())
+                })
+                .collect();
+            used.sort_unstable();
+            used.dedup();
+            for label in used {
+                uses.entry(label).or_insert_with(Vec::new).push(user);
+            }
+        }
+        Self {
+            uses: Arc::new(uses),
+        }
+    }
+
+    /// Returns the label tokens of a proof, for both normal and compressed proofs.
+    pub(crate) fn proof_labels<'a>(sref: &StatementRef<'a>) -> Vec<&'a [u8]> {
+        let tokens: Vec<&[u8]> = (0..sref.proof_len())
+            .map(|i| sref.proof_slice_at(i))
+            .collect();
+        if tokens.first() == Some(&&b"("[..]) {
+            tokens
+                .into_iter()
+                .skip(1)
+                .take_while(|&token| token != b")")
+                .collect()
+        } else {
+            tokens.into_iter().filter(|&token| token != b"?").collect()
+        }
+    }
+
+    /// The labels of the theorems referencing the given statement, in database order.
+    pub(crate) fn get(&self, label: Label) -> &[Label] {
+        self.uses.get(&label).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl Renderer {
+    pub(crate) fn get_uses(&self, sref: &StatementRef) -> Vec<String> {
+        let nset = self.db.name_result();
+        match nset.lookup_label(sref.label()) {
+            Some(lookup) => self
+                .uses
+                .get(lookup.atom)
+                .iter()
+                .map(|&label| as_str(nset.atom_name(label)).to_string())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn render_uses(&self, explorer: String, label: String) -> Option<String> {
+        let sref = self.db.statement(label.as_bytes())?;
+        let expression_renderer = self.get_expression_renderer(explorer.clone())?;
+        let header = expression_renderer.get_header();
+        let nav = self.get_nav(&self.db.get_outline_node(sref));
+        let uses: Vec<UseInfo> = self
+            .get_uses(&sref)
+            .into_iter()
+            .filter_map(|label| {
+                let use_sref = self.db.statement(label.as_bytes())?;
+                let expr = expression_renderer
+                    .render_statement(&use_sref, &self.db, true)
+                    .unwrap_or_else(|e| format!("Could not format assertion : {}", e));
+                Some(UseInfo { label, expr })
+            })
+            .collect();
+        let legend = self.get_legend(
+            &expression_renderer,
+            uses.iter().map(|use_info| use_info.label.as_str()),
+        );
+        let info = UsesPageInfo {
+            header,
+            explorer,
+            link: format!("{}/uses", label),
+            label,
+            nav,
+            uses,
+            legend,
+        };
+        Some(
+            self.templates
+                .render("uses", &info)
+                .expect("Failed to render"),
+        )
+    }
+}
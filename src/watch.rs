@@ -0,0 +1,95 @@
+//! Hot reload of the database when the database file or its includes change
+use crate::config::Config;
+use crate::config::DatabaseConfig;
+use crate::statement::DatabaseInfo;
+use crate::statement::Renderer;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// The renderer currently serving pages, swapped when the database is reloaded
+pub(crate) type SharedRenderer = Arc<RwLock<Renderer>>;
+
+/// Interval between two checks of the database files
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The database file and all the files it includes, recursively
+fn watched_files(database: &Path) -> Vec<PathBuf> {
+    let include_regex = Regex::new(r"\$\[\s+(\S+)\s+\$\]").unwrap();
+    let dir = database.parent().unwrap_or_else(|| Path::new("."));
+    let mut files = vec![database.to_path_buf()];
+    let mut index = 0;
+    while index < files.len() {
+        if let Ok(contents) = fs::read_to_string(&files[index]) {
+            for caps in include_regex.captures_iter(&contents) {
+                let include = dir.join(&caps[1]);
+                if !files.contains(&include) {
+                    files.push(include);
+                }
+            }
+        }
+        index += 1;
+    }
+    files
+}
+
+fn modification_time(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file).and_then(|m| m.modified()).ok()
+}
+
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files.iter().map(|file| modification_time(file)).collect()
+}
+
+/// Checks the database files periodically, and when they changed,
+/// re-runs the passes on a copy of the database and swaps the renderer.
+/// The previous version is served until the new one is ready.
+pub(crate) async fn watch(
+    config: Config,
+    database: DatabaseConfig,
+    databases: Vec<DatabaseInfo>,
+    renderer: SharedRenderer,
+) {
+    let file = PathBuf::from(&database.file);
+    let mut files = watched_files(&file);
+    let mut times = modification_times(&files);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        // Taken before reloading, so that changes made during the reload trigger another one
+        let snapshot = modification_times(&files);
+        if snapshot == times {
+            continue;
+        }
+        println!("Database {} changed, reloading...", database.info.name);
+        let mut db = renderer.read().unwrap().db.clone();
+        let reload_config = config.clone();
+        let reload_database = database.clone();
+        let reload_databases = databases.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            crate::parse_db(&mut db, &reload_database.file)?;
+            crate::build_renderer(&reload_config, &reload_database, &reload_databases, db)
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("{}", e)));
+        match result {
+            Ok(new_renderer) => *renderer.write().unwrap() = new_renderer,
+            Err(message) => println!("Error: {}, still serving the previous version", message),
+        }
+        // Keep the snapshot for the files still included, and take newly included files as they are now
+        let new_files = watched_files(&file);
+        times = new_files
+            .iter()
+            .map(|new_file| match files.iter().position(|f| f == new_file) {
+                Some(index) => snapshot[index],
+                None => modification_time(new_file),
+            })
+            .collect();
+        files = new_files;
+    }
+}
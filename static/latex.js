@@ -0,0 +1,74 @@
+// LaTeX explorer: copy the LaTeX source of formulas, and optionally typeset them with MathJax.
+(function () {
+    // Where to load MathJax from, according to the explorer's `mathjax` setting: none when native
+    var MATHJAX = document.currentScript && document.currentScript.dataset.mathjax;
+    var STORAGE_KEY = "latex-typeset";
+
+    function copy(button) {
+        var source = button.parentNode.querySelector("code").textContent;
+        navigator.clipboard.writeText(source).then(function () {
+            button.textContent = "Copied";
+            setTimeout(function () { button.textContent = "Copy"; }, 1500);
+        });
+    }
+
+    function loadMathJax(callback) {
+        if (window.MathJax && window.MathJax.typesetPromise) {
+            callback();
+            return;
+        }
+        window.MathJax = { startup: { typeset: false, ready: function () {
+            MathJax.startup.defaultReady();
+            callback();
+        } } };
+        var script = document.createElement("script");
+        script.src = MATHJAX;
+        script.async = true;
+        document.head.appendChild(script);
+    }
+
+    function typeset(enabled) {
+        document.body.classList.toggle("latex-typeset", enabled);
+        if (!enabled) {
+            return;
+        }
+        var formulas = [];
+        document.querySelectorAll("span.latex").forEach(function (span) {
+            if (!span.querySelector(".latex-output")) {
+                var output = document.createElement("span");
+                output.className = "latex-output";
+                output.textContent = "\\(" + span.querySelector("code").textContent + "\\)";
+                span.insertBefore(output, span.firstChild);
+                formulas.push(output);
+            }
+        });
+        loadMathJax(function () { MathJax.typesetPromise(formulas); });
+    }
+
+    document.addEventListener("DOMContentLoaded", function () {
+        document.querySelectorAll("button.copy-latex").forEach(function (button) {
+            button.addEventListener("click", function () { copy(button); });
+        });
+        if (!MATHJAX) {
+            return;
+        }
+        var list = document.querySelector("nav.typesettings ul");
+        if (list) {
+            var item = document.createElement("li");
+            var toggle = document.createElement("a");
+            toggle.href = "#";
+            toggle.textContent = "Typeset formulas";
+            toggle.addEventListener("click", function (event) {
+                event.preventDefault();
+                var enabled = !document.body.classList.contains("latex-typeset");
+                localStorage.setItem(STORAGE_KEY, enabled ? "on" : "off");
+                typeset(enabled);
+            });
+            item.appendChild(toggle);
+            list.appendChild(item);
+        }
+        if (localStorage.getItem(STORAGE_KEY) === "on") {
+            typeset(true);
+        }
+    });
+})();
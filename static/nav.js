@@ -0,0 +1,28 @@
+// Keyboard shortcuts for the navigation links:
+// p and n for the previous and next statements, u to go up, f and l for the first and last in chapter.
+// Letters are used so that the arrows, Home and End keys still scroll the page.
+(function () {
+    var SHORTCUTS = {
+        "p": "nav-prev",
+        "n": "nav-next",
+        "u": "nav-up",
+        "f": "nav-first",
+        "l": "nav-last"
+    };
+
+    document.addEventListener("keydown", function (event) {
+        if (event.altKey || event.ctrlKey || event.metaKey || event.shiftKey) {
+            return;
+        }
+        var target = event.target;
+        if (target.isContentEditable || /^(INPUT|TEXTAREA|SELECT)$/.test(target.tagName)) {
+            return;
+        }
+        var id = SHORTCUTS[event.key];
+        var link = id && document.getElementById(id);
+        if (link) {
+            event.preventDefault();
+            window.location.href = link.href;
+        }
+    });
+})();
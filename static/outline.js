@@ -0,0 +1,69 @@
+// Outline: keep the expanded headings in the `open` query parameter, without reloading the page.
+(function () {
+    function openParameter() {
+        var refs = [];
+        document.querySelectorAll("ol.outline details[open]").forEach(function (details) {
+            refs.push(details.dataset.ref);
+        });
+        return refs.join(",");
+    }
+
+    function update() {
+        var open = openParameter();
+        history.replaceState(null, "", open ? "?open=" + open : location.pathname);
+        // Keep the expanded headings when switching to another typesetting
+        document.querySelectorAll("nav.typesettings a").forEach(function (link) {
+            link.search = open ? "?open=" + open : "";
+        });
+    }
+
+    // The statements of the headings collapsed when the page was rendered are fetched when first expanded,
+    // and inserted among the subheadings in outline order
+    function load(details) {
+        details.removeAttribute("data-pending");
+        var api = document.querySelector("ol.outline").dataset.api;
+        var list = details.querySelector("ol");
+        fetch(api + "?ref=" + details.dataset.ref)
+            .then(function (response) {
+                return response.json();
+            })
+            .then(function (node) {
+                node.children.forEach(function (child) {
+                    var item = document.getElementById("n-" + child.chapter_ref);
+                    if (!item) {
+                        item = document.createElement("li");
+                        item.id = "n-" + child.chapter_ref;
+                        var link = document.createElement("a");
+                        link.href = child.name;
+                        link.className = "label";
+                        link.textContent = child.name;
+                        item.appendChild(link);
+                    }
+                    list.appendChild(item);
+                });
+            })
+            .catch(function () {
+                details.setAttribute("data-pending", "");
+            });
+    }
+
+    document.addEventListener("DOMContentLoaded", function () {
+        // The toggle event does not bubble
+        document.addEventListener("toggle", function (event) {
+            if (event.target.open && event.target.hasAttribute("data-pending")) {
+                load(event.target);
+            }
+            update();
+        }, true);
+        var collapse = document.getElementById("collapse-all");
+        if (collapse) {
+            collapse.addEventListener("click", function (event) {
+                event.preventDefault();
+                document.querySelectorAll("ol.outline details[open]").forEach(function (details) {
+                    details.open = false;
+                });
+                update();
+            });
+        }
+    });
+})();
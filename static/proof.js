@@ -0,0 +1,82 @@
+// Highlights the proof step a hypothesis reference points to, when hovering it,
+// and fetches the substitutions of the steps when one of their panels is first expanded.
+(function () {
+    var substitutions = null;
+
+    function target(link) {
+        var name = decodeURIComponent(link.hash.substring(1));
+        var anchor = document.getElementsByName(name)[0];
+        return anchor && anchor.parentNode.closest("tr, summary, li");
+    }
+
+    function cell(row, className, html) {
+        var cell = row.insertCell();
+        cell.className = className;
+        cell.innerHTML = html;
+    }
+
+    // Fills the panels of all the steps, from the proof given by the JSON API
+    function fill(proof) {
+        proof.steps.forEach(function (step) {
+            var details = document.querySelector('details.substitutions[data-step="' + step.id + '"]');
+            if (!details || !step.substitutions) {
+                return;
+            }
+            if (!step.substitutions.length) {
+                details.appendChild(document.createTextNode("None"));
+                return;
+            }
+            var table = document.createElement("table");
+            step.substitutions.forEach(function (substitution) {
+                var row = table.insertRow();
+                cell(row, "subst-var", substitution.variable);
+                cell(row, "subst-eq", ":=");
+                cell(row, "subst-expr", substitution.expr);
+            });
+            details.appendChild(table);
+            if (window.MathJax && window.MathJax.typesetPromise) {
+                MathJax.typesetPromise([table]);
+            }
+        });
+    }
+
+    function loadSubstitutions() {
+        if (substitutions) {
+            return;
+        }
+        var api = document.querySelector("section.proof").dataset.api;
+        substitutions = fetch(api)
+            .then(function (response) {
+                return response.json();
+            })
+            .then(fill)
+            .catch(function () {
+                // Try again when a panel is expanded next time
+                substitutions = null;
+            });
+    }
+
+    document.addEventListener("DOMContentLoaded", function () {
+        document.querySelectorAll("section.proof a.step").forEach(function (link) {
+            link.addEventListener("mouseenter", function () {
+                var step = target(link);
+                if (step) {
+                    step.classList.add("highlight");
+                }
+            });
+            link.addEventListener("mouseleave", function () {
+                var step = target(link);
+                if (step) {
+                    step.classList.remove("highlight");
+                }
+            });
+        });
+        document.querySelectorAll("details.substitutions").forEach(function (details) {
+            details.addEventListener("toggle", function () {
+                if (details.open) {
+                    loadSubstitutions();
+                }
+            });
+        });
+    });
+})();